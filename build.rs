@@ -7,7 +7,7 @@ use std::{
 };
 
 use proc_macro2::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote};
 use syntect::{highlighting::ThemeSet, html::ClassStyle};
 
 fn main() {
@@ -17,14 +17,16 @@ fn main() {
     let out = PathBuf::from(env::var("OUT_DIR").unwrap());
 
     let (webfonts, base_route) = render_webfonts(&root);
-    let main_css = render_main_css(&root, &out, base_route);
+    let dark_css = render_main_css(&root, &out, &base_route, Theme::Dark);
+    let light_css = render_main_css(&root, &out, &base_route, Theme::Light);
     let favicons = render_favicons(&root);
 
     let syntax = quote! {
         use std::sync::LazyLock;
         use axum_extra::headers::ETag;
 
-        #main_css
+        #dark_css
+        #light_css
         #webfonts
         #favicons
     };
@@ -32,23 +34,44 @@ fn main() {
     fs::write(out.join("assets.rs"), syntax.to_string()).unwrap();
 }
 
-fn render_main_css(root: &str, out: &Path, webfonts_route: String) -> TokenStream {
+#[derive(Clone, Copy)]
+enum Theme {
+    Dark,
+    Light,
+}
+
+impl Theme {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Dark => "dark",
+            Self::Light => "light",
+        }
+    }
+
+    fn highlight(self) -> &'static [u8] {
+        match self {
+            Self::Dark => include_bytes!("assets/OneHalfDark.tmTheme"),
+            Self::Light => include_bytes!("assets/OneHalfLight.tmTheme"),
+        }
+    }
+}
+
+fn render_main_css(root: &str, out: &Path, webfonts_route: &str, theme: Theme) -> TokenStream {
     let highlight = syntect::html::css_for_theme_with_class_style(
-        &ThemeSet::load_from_reader(&mut BufReader::new(Cursor::new(
-            &include_bytes!("assets/OneHalfDark.tmTheme")[..],
-        )))
-        .unwrap(),
+        &ThemeSet::load_from_reader(&mut BufReader::new(Cursor::new(theme.highlight()))).unwrap(),
         ClassStyle::SpacedPrefixed {
             prefix: "highlight-",
         },
     )
     .unwrap();
+    let light_theme = matches!(theme, Theme::Light);
 
     let scss = format!(
         r#"
         @charset "utf-8";
         $fa-font-path: "{webfonts_route}";
         $fc-font-path: "{webfonts_route}";
+        $light-theme: {light_theme};
         @import "assets/main.sass";
         "#
     );
@@ -74,15 +97,21 @@ fn render_main_css(root: &str, out: &Path, webfonts_route: String) -> TokenStrea
         hasher.write(css.as_bytes());
         hasher.finish()
     };
-    let route = format!("/main-{hash:016x}.css");
+    let name = theme.name();
+    let route = format!("/{name}-{hash:016x}.css");
     let etag = format!("W/\"{hash:016x}\"");
 
-    fs::write(out.join("main.css"), css).unwrap();
+    fs::write(out.join(format!("{name}.css")), css).unwrap();
+
+    let file = format!("/{name}.css");
+    let route_ident = format_ident!("{}_CSS_ROUTE", name.to_uppercase());
+    let content_ident = format_ident!("{}_CSS_CONTENT", name.to_uppercase());
+    let hash_ident = format_ident!("{}_CSS_HASH", name.to_uppercase());
 
     quote! {
-        pub const MAIN_CSS_ROUTE: &str = #route;
-        pub static MAIN_CSS_CONTENT: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/main.css"));
-        pub static MAIN_CSS_HASH: LazyLock<ETag> = LazyLock::new(|| #etag.parse().unwrap());
+        pub const #route_ident: &str = #route;
+        pub static #content_ident: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), #file));
+        pub static #hash_ident: LazyLock<ETag> = LazyLock::new(|| #etag.parse().unwrap());
     }
 }
 
@@ -1,6 +1,6 @@
 use std::fmt;
 
-use serde::de::{self, Deserializer, Visitor};
+use serde::de::{self, Deserialize, DeserializeOwned, Deserializer, IntoDeserializer, Visitor};
 
 pub fn form_bool<'de, D>(deserializer: D) -> Result<bool, D::Error>
 where
@@ -31,6 +31,18 @@ impl Visitor<'_> for FormBoolVisitor {
     }
 }
 
+/// Deserialize form values like the ones of a `<select>`, where an empty string means no value.
+pub fn empty_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    match Option::<String>::deserialize(deserializer)?.as_deref() {
+        None | Some("") => Ok(None),
+        Some(value) => T::deserialize(value.into_deserializer()).map(Some),
+    }
+}
+
 pub fn repo_name<'de, D>(deserializer: D) -> Result<String, D::Error>
 where
     D: Deserializer<'de>,
@@ -54,3 +66,47 @@ impl Visitor<'_> for RepoNameVisitor {
         Ok(v.strip_suffix(".git").unwrap_or(v).to_owned())
     }
 }
+
+pub fn line_range<'de, D>(deserializer: D) -> Result<Option<(usize, usize)>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_str(LineRangeVisitor)
+}
+
+struct LineRangeVisitor;
+
+impl Visitor<'_> for LineRangeVisitor {
+    type Value = Option<(usize, usize)>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("line number like `42` or line range like `42-60` or `L42-L60`")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if v.is_empty() {
+            return Ok(None);
+        }
+
+        let parse = |line: &str| {
+            line.trim()
+                .trim_start_matches('L')
+                .parse::<usize>()
+                .ok()
+                .filter(|&line| line > 0)
+                .ok_or_else(|| E::custom(format!("invalid line number `{line}`")))
+        };
+
+        let (start, end) = if let Some((start, end)) = v.split_once('-') {
+            (parse(start)?, parse(end)?)
+        } else {
+            let line = parse(v)?;
+            (line, line)
+        };
+
+        Ok(Some((start.min(end), start.max(end))))
+    }
+}
@@ -8,6 +8,9 @@ use unidirs::{Directories, UnifiedDirs};
 // no good recovery case other than throwing an error and shutting down.
 pub static DIRS: LazyLock<Dirs> = LazyLock::new(|| Dirs::new().unwrap());
 
+/// Longest tag name that still fits into a single path component when hex encoded.
+const MAX_HEX_TAG_LEN: usize = 255 / 2;
+
 pub struct Dirs {
     data_dir: Utf8PathBuf,
     users_dir: Utf8PathBuf,
@@ -46,6 +49,12 @@ impl Dirs {
         self.data_dir.join("~settings.json")
     }
 
+    // <data>/syntaxes/
+    #[inline]
+    pub fn syntaxes_dir(&self) -> Utf8PathBuf {
+        self.data_dir.join("syntaxes")
+    }
+
     // <data>/users
     #[inline]
     pub fn users_dir(&self) -> &Utf8Path {
@@ -129,4 +138,82 @@ impl Dirs {
         dir.push("repo.git");
         dir
     }
+
+    // <data>/users/<user>/repos/<repo>/releases/
+    #[inline]
+    pub fn repo_releases_dir(&self, user: &str, repo: &str) -> Utf8PathBuf {
+        let mut dir = self.repo_dir(user, repo);
+        dir.push("releases");
+        dir
+    }
+
+    // <data>/users/<user>/repos/<repo>/releases/<hex(tag)>/
+    //
+    // Tag names can contain slashes and other characters that aren't safe to use as a single path
+    // component, so they're hex encoded. Tags that would exceed the file name limit of 255 bytes
+    // that way are hashed instead, with a prefix that can't clash with a hex encoded name.
+    #[inline]
+    pub fn release_dir(&self, user: &str, repo: &str, tag: &str) -> Utf8PathBuf {
+        let mut dir = self.repo_releases_dir(user, repo);
+        if tag.len() <= MAX_HEX_TAG_LEN {
+            dir.push(hex::encode(tag));
+        } else {
+            // Unwrap: Hashing in-memory data can't fail.
+            let hash = git2::Oid::hash_object(git2::ObjectType::Blob, tag.as_bytes()).unwrap();
+            dir.push(format!("sha1-{hash}"));
+        }
+        dir
+    }
+
+    // <data>/users/<user>/repos/<repo>/releases/<hex(tag)>/release.json
+    #[inline]
+    pub fn release_info_file(&self, user: &str, repo: &str, tag: &str) -> Utf8PathBuf {
+        let mut dir = self.release_dir(user, repo, tag);
+        dir.push("release.json");
+        dir
+    }
+
+    // <data>/users/<user>/repos/<repo>/releases/<hex(tag)>/~release.json
+    #[inline]
+    pub fn release_info_temp_file(&self, user: &str, repo: &str, tag: &str) -> Utf8PathBuf {
+        let mut dir = self.release_dir(user, repo, tag);
+        dir.push("~release.json");
+        dir
+    }
+
+    // <data>/users/<user>/repos/<repo>/releases/<hex(tag)>/assets/
+    #[inline]
+    pub fn release_assets_dir(&self, user: &str, repo: &str, tag: &str) -> Utf8PathBuf {
+        let mut dir = self.release_dir(user, repo, tag);
+        dir.push("assets");
+        dir
+    }
+
+    // <data>/users/<user>/repos/<repo>/releases/<hex(tag)>/assets/<asset>
+    #[inline]
+    pub fn release_asset_file(
+        &self,
+        user: &str,
+        repo: &str,
+        tag: &str,
+        asset: &str,
+    ) -> Utf8PathBuf {
+        let mut dir = self.release_assets_dir(user, repo, tag);
+        dir.push(asset);
+        dir
+    }
+
+    // <data>/users/<user>/repos/<repo>/releases/<hex(tag)>/assets/~<asset>
+    #[inline]
+    pub fn release_asset_temp_file(
+        &self,
+        user: &str,
+        repo: &str,
+        tag: &str,
+        asset: &str,
+    ) -> Utf8PathBuf {
+        let mut dir = self.release_assets_dir(user, repo, tag);
+        dir.push(format!("~{asset}"));
+        dir
+    }
 }
@@ -4,12 +4,13 @@ use tracing::{info, instrument};
 
 use crate::{
     cookies::{Cookie, Cookies},
+    dirs::DIRS,
     extract::User,
-    redirect,
+    redirect, render,
     repositories::{SettingsRepository, UserRepository},
     response::{SetCookies, StatusTemplate},
     session::COOKIE_MESSAGE,
-    templates,
+    templates, validate,
 };
 
 #[instrument(skip_all, fields(?user.username))]
@@ -39,6 +40,9 @@ pub async fn settings(
             auth_user: Some(user),
             message,
             onion: settings_repo.get_tor_onion().await.unwrap_or_default(),
+            default_branch: settings_repo.get_default_branch().await.unwrap_or_default(),
+            syntaxes: render::syntaxes(),
+            syntaxes_dir: DIRS.syntaxes_dir(),
         },
         cookies,
     ))
@@ -115,3 +119,42 @@ pub async fn settings_tor_post(
 
     Ok(SetCookies::new(redirect::to_admin_settings(), cookies))
 }
+
+#[derive(Deserialize)]
+pub struct BranchSettings {
+    default_branch: String,
+}
+
+#[instrument(skip_all, fields(?user.username))]
+pub async fn settings_branch_post(
+    User(user): User,
+    mut cookies: Cookies,
+    Form(settings): Form<BranchSettings>,
+) -> Result<impl IntoResponse, StatusTemplate> {
+    info!("got admin settings request (branch)");
+
+    let user_repo = UserRepository::for_user(&user.username);
+    let settings_repo = SettingsRepository::new();
+
+    if !user_repo.exists().await || !user_repo.load_info().await.unwrap().admin {
+        return Err(StatusTemplate(StatusCode::NOT_FOUND));
+    }
+
+    let branch = settings.default_branch.trim();
+    let message = if branch.is_empty() {
+        settings_repo.set_default_branch(None).await.unwrap();
+        templates::admin::ServerSettingsMessage::Success
+    } else if validate::branch_name(branch) {
+        settings_repo
+            .set_default_branch(Some(branch.to_owned()))
+            .await
+            .unwrap();
+        templates::admin::ServerSettingsMessage::Success
+    } else {
+        templates::admin::ServerSettingsMessage::InvalidBranchName
+    };
+
+    cookies.add(Cookie::new(COOKIE_MESSAGE, message.as_ref()));
+
+    Ok(SetCookies::new(redirect::to_admin_settings(), cookies))
+}
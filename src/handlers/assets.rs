@@ -18,7 +18,7 @@ use crate::assets;
 type AssetResponse = Result<(HeaderMap, &'static [u8]), (HeaderMap, StatusCode)>;
 
 /// Max age setting for the `Cache-Control` header, currently set to **2 years**.
-const CACHE_MAX_AGE: Duration = Duration::from_secs(63_072_000);
+const CACHE_MAX_AGE: Duration = Duration::from_hours(17_520);
 
 pub async fn favicon_svg(
     if_modified_since: Option<TypedHeader<IfModifiedSince>>,
@@ -34,14 +34,28 @@ pub async fn favicon_svg(
     )
 }
 
-pub async fn main_css(
+pub async fn dark_css(
     if_modified_since: Option<TypedHeader<IfModifiedSince>>,
     if_none_match: Option<TypedHeader<IfNoneMatch>>,
 ) -> impl IntoResponse {
-    info!("got assets main-css request");
+    info!("got assets dark-css request");
     asset_reply(
-        &assets::MAIN_CSS_HASH,
-        assets::MAIN_CSS_CONTENT,
+        &assets::DARK_CSS_HASH,
+        assets::DARK_CSS_CONTENT,
+        mime::TEXT_CSS,
+        if_modified_since,
+        if_none_match,
+    )
+}
+
+pub async fn light_css(
+    if_modified_since: Option<TypedHeader<IfModifiedSince>>,
+    if_none_match: Option<TypedHeader<IfNoneMatch>>,
+) -> impl IntoResponse {
+    info!("got assets light-css request");
+    asset_reply(
+        &assets::LIGHT_CSS_HASH,
+        assets::LIGHT_CSS_CONTENT,
         mime::TEXT_CSS,
         if_modified_since,
         if_none_match,
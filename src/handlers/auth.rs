@@ -24,7 +24,13 @@ pub async fn login(mut cookies: Cookies) -> impl IntoResponse {
         cookies.remove(COOKIE_ERROR);
     }
 
-    SetCookies::new(templates::Login { error }, cookies)
+    SetCookies::new(
+        templates::Login {
+            auth_user: None,
+            error,
+        },
+        cookies,
+    )
 }
 
 #[derive(Deserialize)]
@@ -86,7 +92,13 @@ pub async fn register(mut cookies: Cookies) -> impl IntoResponse {
         cookies.remove(COOKIE_ERROR);
     }
 
-    SetCookies::new(templates::Register { error }, cookies)
+    SetCookies::new(
+        templates::Register {
+            auth_user: None,
+            error,
+        },
+        cookies,
+    )
 }
 
 #[derive(Deserialize)]
@@ -0,0 +1,173 @@
+use axum::{
+    extract::{Form, Path, Query},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde::Deserialize;
+use tracing::{info, instrument};
+
+use super::repo::BasePath;
+use crate::{
+    cookies::{Cookie, Cookies},
+    extract::User,
+    models::{BranchCreateOutcome, BranchDeleteOutcome, BranchRenameOutcome},
+    redirect,
+    repositories::RepoRepository,
+    response::{SetCookies, StatusTemplate},
+    session::COOKIE_MESSAGE,
+    templates::{self, repo::RepoSettingsMessage},
+    validate,
+};
+
+#[derive(Deserialize)]
+pub struct BranchCreate {
+    name: String,
+    /// Revspec of the commit that the new branch starts at.
+    from: String,
+}
+
+#[instrument(skip_all, fields(?path.user, ?path.repo, ?create.name, ?create.from))]
+pub async fn create_post(
+    User(user): User,
+    Path(path): Path<BasePath>,
+    mut cookies: Cookies,
+    Form(create): Form<BranchCreate>,
+) -> Result<impl IntoResponse, StatusTemplate> {
+    info!("got branch create request");
+
+    let repo_repo = RepoRepository::for_repo(&path.user, &path.repo);
+
+    if !repo_repo.can_write_refs(&user.username) || !repo_repo.exists().await {
+        return Err(StatusTemplate(StatusCode::NOT_FOUND));
+    }
+
+    let message = if validate::branch_name(&create.name) {
+        match repo_repo
+            .create_branch(&create.name, &create.from)
+            .await
+            .unwrap()
+        {
+            BranchCreateOutcome::Created => RepoSettingsMessage::BranchCreated,
+            BranchCreateOutcome::AlreadyExists => RepoSettingsMessage::BranchExists,
+            BranchCreateOutcome::UnknownRev => RepoSettingsMessage::UnknownRev,
+        }
+    } else {
+        RepoSettingsMessage::InvalidBranchName
+    };
+
+    cookies.add(Cookie::new(COOKIE_MESSAGE, message.as_ref()));
+
+    Ok(SetCookies::new(
+        redirect::to_repo_settings(&path.user, &path.repo),
+        cookies,
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct BranchRename {
+    branch: String,
+    name: String,
+}
+
+#[instrument(skip_all, fields(?path.user, ?path.repo, ?rename.branch, ?rename.name))]
+pub async fn rename_post(
+    User(user): User,
+    Path(path): Path<BasePath>,
+    mut cookies: Cookies,
+    Form(rename): Form<BranchRename>,
+) -> Result<impl IntoResponse, StatusTemplate> {
+    info!("got branch rename request");
+
+    let repo_repo = RepoRepository::for_repo(&path.user, &path.repo);
+
+    if !repo_repo.can_write_refs(&user.username) || !repo_repo.exists().await {
+        return Err(StatusTemplate(StatusCode::NOT_FOUND));
+    }
+
+    let message = if validate::branch_name(&rename.name) {
+        match repo_repo
+            .rename_branch(&rename.branch, &rename.name)
+            .await
+            .unwrap()
+        {
+            BranchRenameOutcome::Renamed => RepoSettingsMessage::BranchRenamed,
+            BranchRenameOutcome::AlreadyExists => RepoSettingsMessage::BranchExists,
+            BranchRenameOutcome::NotFound => RepoSettingsMessage::UnknownBranch,
+        }
+    } else {
+        RepoSettingsMessage::InvalidBranchName
+    };
+
+    cookies.add(Cookie::new(COOKIE_MESSAGE, message.as_ref()));
+
+    Ok(SetCookies::new(
+        redirect::to_repo_settings(&path.user, &path.repo),
+        cookies,
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct BranchQuery {
+    pub branch: String,
+}
+
+#[instrument(skip_all, fields(?path.user, ?path.repo, ?query.branch))]
+pub async fn delete(
+    User(user): User,
+    Path(path): Path<BasePath>,
+    Query(query): Query<BranchQuery>,
+) -> Result<impl IntoResponse, StatusTemplate> {
+    info!("got branch delete request");
+
+    let repo_repo = RepoRepository::for_repo(&path.user, &path.repo);
+
+    if !repo_repo.can_write_refs(&user.username) || !repo_repo.exists().await {
+        return Err(StatusTemplate(StatusCode::NOT_FOUND));
+    }
+
+    let default_branch = repo_repo.get_branch().await.unwrap();
+    let branch = repo_repo
+        .list_branch_info()
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|branch| branch.name == query.branch && branch.name != default_branch)
+        .ok_or(StatusTemplate(StatusCode::NOT_FOUND))?;
+
+    Ok(templates::repo::BranchDelete {
+        auth_user: Some(user),
+        user: path.user,
+        repo: path.repo,
+        default_branch,
+        branch,
+    })
+}
+
+#[instrument(skip_all, fields(?path.user, ?path.repo, ?query.branch))]
+pub async fn delete_post(
+    User(user): User,
+    Path(path): Path<BasePath>,
+    Query(query): Query<BranchQuery>,
+    mut cookies: Cookies,
+) -> Result<impl IntoResponse, StatusTemplate> {
+    info!("got branch delete request");
+
+    let repo_repo = RepoRepository::for_repo(&path.user, &path.repo);
+
+    if !repo_repo.can_write_refs(&user.username) || !repo_repo.exists().await {
+        return Err(StatusTemplate(StatusCode::NOT_FOUND));
+    }
+
+    let message = match repo_repo.delete_branch(&query.branch).await.unwrap() {
+        BranchDeleteOutcome::Deleted => RepoSettingsMessage::BranchDeleted,
+        BranchDeleteOutcome::NotFound => RepoSettingsMessage::UnknownBranch,
+        BranchDeleteOutcome::IsDefault => RepoSettingsMessage::DefaultBranch,
+    };
+
+    cookies.add(Cookie::new(COOKIE_MESSAGE, message.as_ref()));
+
+    Ok(SetCookies::new(
+        redirect::to_repo_settings(&path.user, &path.repo),
+        cookies,
+    ))
+}
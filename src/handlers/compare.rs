@@ -0,0 +1,141 @@
+use axum::{
+    extract::{Form, Path, Query},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde::Deserialize;
+use tracing::{info, instrument};
+
+use super::repo::BasePath;
+use crate::{
+    cookies::{Cookie, Cookies},
+    extract::User,
+    models::{MergeOutcome, MergeStrategy},
+    redirect,
+    repositories::RepoRepository,
+    response::{SetCookies, StatusTemplate},
+    session::COOKIE_MESSAGE,
+    templates::{self, repo::MergeMessage},
+    validate,
+};
+
+#[derive(Deserialize)]
+pub struct CompareQuery {
+    /// Branch to merge into, defaulting to the default branch of the repo.
+    pub base: Option<String>,
+    /// Revspec to merge, usually another branch. Nothing is compared without it.
+    pub head: Option<String>,
+}
+
+#[instrument(skip_all, fields(?user.username, ?path.user, ?path.repo, ?query.base, ?query.head))]
+pub async fn compare(
+    User(user): User,
+    Path(path): Path<BasePath>,
+    Query(query): Query<CompareQuery>,
+    mut cookies: Cookies,
+) -> Result<impl IntoResponse, StatusTemplate> {
+    info!("got repo compare request");
+
+    let repo_repo = RepoRepository::for_repo(&path.user, &path.repo);
+
+    if !repo_repo.exists().await || !repo_repo.visible(&user.username, &path.user).await.unwrap() {
+        return Err(StatusTemplate(StatusCode::NOT_FOUND));
+    }
+
+    let message = cookies
+        .get(COOKIE_MESSAGE)
+        .and_then(|cookie| cookie.value().parse().ok());
+
+    if message.is_some() {
+        cookies.remove(COOKIE_MESSAGE);
+    }
+
+    let base = match query.base {
+        Some(base) => base,
+        None => repo_repo.get_branch().await.unwrap(),
+    };
+    let branches = repo_repo.list_branches().await.unwrap();
+
+    let comparison = match &query.head {
+        Some(head) => Some(
+            repo_repo
+                .compare(&base, head)
+                .await
+                .unwrap()
+                .ok_or(StatusTemplate(StatusCode::NOT_FOUND))?,
+        ),
+        None => None,
+    };
+
+    Ok(SetCookies::new(
+        templates::repo::Compare {
+            message,
+            auth_user: Some(user),
+            user: path.user,
+            repo: path.repo,
+            base,
+            head: query.head.unwrap_or_default(),
+            branches,
+            comparison,
+        },
+        cookies,
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct MergeForm {
+    base: String,
+    head: String,
+    /// ID of the base commit that the comparison was made against.
+    base_commit: String,
+    /// ID of the head commit that was reviewed and gets merged.
+    head_commit: String,
+    strategy: MergeStrategy,
+}
+
+#[instrument(skip_all, fields(?path.user, ?path.repo, ?merge.base, ?merge.head, ?merge.strategy))]
+pub async fn merge_post(
+    User(user): User,
+    Path(path): Path<BasePath>,
+    mut cookies: Cookies,
+    Form(merge): Form<MergeForm>,
+) -> Result<impl IntoResponse, StatusTemplate> {
+    info!("got repo merge request");
+
+    let repo_repo = RepoRepository::for_repo(&path.user, &path.repo);
+
+    if user.username != path.user || !repo_repo.exists().await {
+        return Err(StatusTemplate(StatusCode::NOT_FOUND));
+    }
+    if !validate::commit_id(&merge.base_commit) || !validate::commit_id(&merge.head_commit) {
+        return Err(StatusTemplate(StatusCode::BAD_REQUEST));
+    }
+
+    let outcome = repo_repo
+        .merge(
+            &merge.base,
+            &merge.base_commit,
+            &merge.head,
+            &merge.head_commit,
+            merge.strategy,
+            &user.username,
+        )
+        .await
+        .unwrap();
+
+    let message = match outcome {
+        MergeOutcome::Merged => MergeMessage::Merged,
+        MergeOutcome::UpToDate => MergeMessage::UpToDate,
+        MergeOutcome::NotFastForward => MergeMessage::NotFastForward,
+        MergeOutcome::Conflicts => MergeMessage::Conflicts,
+        MergeOutcome::BranchMoved => MergeMessage::BranchMoved,
+        MergeOutcome::UnknownBranch => return Err(StatusTemplate(StatusCode::NOT_FOUND)),
+    };
+
+    cookies.add(Cookie::new(COOKIE_MESSAGE, message.as_ref()));
+
+    Ok(SetCookies::new(
+        redirect::to_repo_compare(&path.user, &path.repo, &merge.base, &merge.head),
+        cookies,
+    ))
+}
@@ -0,0 +1,297 @@
+use axum::{
+    extract::{Form, Multipart, Path, Query},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::Deserialize;
+use tracing::{info, instrument};
+
+use super::repo::{Raw, Tree};
+use crate::{
+    cookies::{Cookie, Cookies},
+    extract::User,
+    models::{CommitOutcome, TreeKind},
+    redirect,
+    repositories::RepoRepository,
+    response::{SetCookies, StatusTemplate},
+    session::COOKIE_MESSAGE,
+    templates::{
+        self,
+        repo::{EditError, UploadMessage},
+    },
+    validate,
+};
+
+/// Maximum size of a single upload request for files that are committed to a repo, currently set
+/// to **100 MiB**.
+pub const FILE_UPLOAD_LIMIT: usize = 100 * 1024 * 1024;
+
+/// Query for the edit routes. Unlike the tree view, this must be the name of a branch, as only
+/// branches can be committed to.
+#[derive(Deserialize)]
+pub struct EditQuery {
+    pub branch: String,
+}
+
+#[instrument(skip_all, fields(?user.username, ?file.user, ?file.repo, ?file.path, ?query.branch))]
+pub async fn edit(
+    User(user): User,
+    Path(file): Path<Raw>,
+    Query(query): Query<EditQuery>,
+) -> Result<impl IntoResponse, StatusTemplate> {
+    info!("got file edit request");
+
+    let repo_repo = RepoRepository::for_repo(&file.user, &file.repo);
+
+    if user.username != file.user
+        || !repo_repo.exists().await
+        || !repo_repo
+            .list_branches()
+            .await
+            .unwrap()
+            .contains(&query.branch)
+    {
+        return Err(StatusTemplate(StatusCode::NOT_FOUND));
+    }
+
+    // Load the content from the resolved commit, so it's exactly what the edit is based on, even
+    // if the branch moves in between.
+    let commit = repo_repo
+        .resolve_commit(&query.branch)
+        .await
+        .unwrap()
+        .ok_or(StatusTemplate(StatusCode::NOT_FOUND))?;
+    let (name, content) = load_text(&repo_repo, &commit, &file.path)
+        .await
+        .ok_or(StatusTemplate(StatusCode::NOT_FOUND))?;
+
+    Ok(templates::repo::Edit {
+        auth_user: Some(user),
+        user: file.user,
+        repo: file.repo,
+        branch: query.branch,
+        path: Utf8PathBuf::from(file.path),
+        commit,
+        message: format!("Update {name}"),
+        crlf: content.contains("\r\n"),
+        content,
+        error: None,
+    })
+}
+
+/// Load the name and content of a text file for editing. Only text that is shown completely can be
+/// edited, everything else would be corrupted. That includes the pointers to files stored in Git
+/// LFS.
+async fn load_text(
+    repo_repo: &RepoRepository<'_, '_>,
+    commit: &str,
+    path: &str,
+) -> Option<(String, String)> {
+    let tree = repo_repo
+        .get_tree_list(commit, Some(Utf8Path::new(path)))
+        .await
+        .unwrap()?;
+
+    match tree.kind {
+        TreeKind::Text(content)
+            if !content.starts_with("version https://git-lfs.github.com/spec/") =>
+        {
+            Some((tree.name, content))
+        }
+        _ => None,
+    }
+}
+
+#[derive(Deserialize)]
+pub struct EditForm {
+    /// ID of the commit that the edit is based on.
+    commit: String,
+    message: String,
+    content: String,
+    /// Whether the file uses Windows line endings. Browsers always submit text with `\r\n`, so
+    /// this is needed to restore the original line endings.
+    #[serde(default, deserialize_with = "crate::de::form_bool")]
+    crlf: bool,
+}
+
+#[instrument(skip_all, fields(?user.username, ?file.user, ?file.repo, ?file.path, ?query.branch))]
+pub async fn edit_post(
+    User(user): User,
+    Path(file): Path<Raw>,
+    Query(query): Query<EditQuery>,
+    Form(form): Form<EditForm>,
+) -> Result<Response, StatusTemplate> {
+    info!("got file edit request");
+
+    let repo_repo = RepoRepository::for_repo(&file.user, &file.repo);
+
+    if user.username != file.user || !repo_repo.exists().await {
+        return Err(StatusTemplate(StatusCode::NOT_FOUND));
+    }
+    if !validate::commit_id(&form.commit) {
+        return Err(StatusTemplate(StatusCode::BAD_REQUEST));
+    }
+    // The form may only change existing text files, the same that can be loaded for editing.
+    if load_text(&repo_repo, &form.commit, &file.path)
+        .await
+        .is_none()
+    {
+        return Err(StatusTemplate(StatusCode::NOT_FOUND));
+    }
+
+    let path = Utf8PathBuf::from(file.path);
+    let mut content = form.content.replace("\r\n", "\n");
+    if form.crlf {
+        content = content.replace('\n', "\r\n");
+    }
+
+    let message = match form.message.trim() {
+        "" => format!("Update {}", path.file_name().unwrap_or_default()),
+        message => message.to_owned(),
+    };
+
+    let outcome = repo_repo
+        .commit_files(
+            &query.branch,
+            &form.commit,
+            vec![(path.clone(), content.into_bytes())],
+            &message,
+            &user.username,
+        )
+        .await
+        .unwrap();
+
+    let (status, error) = match outcome {
+        CommitOutcome::Committed(_) | CommitOutcome::Unchanged => {
+            return Ok(redirect::to_repo_tree(
+                &file.user,
+                &file.repo,
+                path.as_str(),
+                &query.branch,
+            )
+            .into_response());
+        }
+        CommitOutcome::BranchMoved => (StatusCode::CONFLICT, EditError::BranchMoved),
+        CommitOutcome::TooLarge(_) => (StatusCode::PAYLOAD_TOO_LARGE, EditError::TooLarge),
+        CommitOutcome::LfsTracked(_) => (StatusCode::UNPROCESSABLE_ENTITY, EditError::LfsTracked),
+        CommitOutcome::UnknownBranch | CommitOutcome::InvalidPath(_) => {
+            return Err(StatusTemplate(StatusCode::NOT_FOUND));
+        }
+    };
+
+    // Show the form again, so the changes aren't lost and can be copied somewhere safe.
+    Ok((
+        status,
+        templates::repo::Edit {
+            auth_user: Some(user),
+            user: file.user,
+            repo: file.repo,
+            branch: query.branch,
+            path,
+            commit: form.commit,
+            message,
+            content: form.content,
+            crlf: form.crlf,
+            error: Some(error),
+        },
+    )
+        .into_response())
+}
+
+/// Commit uploaded files into a directory of a branch. Besides the `files`, the form must send the
+/// `commit` that the upload is based on and may send a commit `message`.
+#[instrument(skip_all, fields(?user.username, ?dir.user, ?dir.repo, ?dir.path, ?query.branch))]
+pub async fn upload_post(
+    User(user): User,
+    Path(dir): Path<Tree>,
+    Query(query): Query<EditQuery>,
+    mut cookies: Cookies,
+    mut multipart: Multipart,
+) -> Result<impl IntoResponse, StatusTemplate> {
+    info!("got file upload request");
+
+    let repo_repo = RepoRepository::for_repo(&dir.user, &dir.repo);
+
+    if user.username != dir.user || !repo_repo.exists().await {
+        return Err(StatusTemplate(StatusCode::NOT_FOUND));
+    }
+
+    let path = dir.path.as_deref().unwrap_or_default().trim_matches('/');
+    let mut commit = None;
+    let mut message = String::new();
+    let mut files = Vec::new();
+    let mut invalid_name = false;
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| StatusTemplate(e.status()))?
+    {
+        match field.name() {
+            Some("commit") => {
+                commit = Some(field.text().await.map_err(|e| StatusTemplate(e.status()))?);
+            }
+            Some("message") => {
+                message = field.text().await.map_err(|e| StatusTemplate(e.status()))?;
+            }
+            Some("files") => {
+                // Browsers send an empty file name if no file was selected at all.
+                let name = match field.file_name() {
+                    Some(name) if !name.is_empty() => name.to_owned(),
+                    _ => continue,
+                };
+
+                if !validate::file_name(&name) {
+                    invalid_name = true;
+                    break;
+                }
+
+                let content = field
+                    .bytes()
+                    .await
+                    .map_err(|e| StatusTemplate(e.status()))?;
+                files.push((Utf8PathBuf::from(path).join(name), content.to_vec()));
+            }
+            _ => {}
+        }
+    }
+
+    let commit = commit
+        .filter(|commit| validate::commit_id(commit))
+        .ok_or(StatusTemplate(StatusCode::BAD_REQUEST))?;
+
+    let message = if invalid_name {
+        UploadMessage::InvalidName
+    } else if files.is_empty() {
+        UploadMessage::Unchanged
+    } else {
+        let message = match message.trim() {
+            "" if files.len() == 1 => format!("Add {}", files[0].0.file_name().unwrap_or_default()),
+            "" => format!("Add {} files", files.len()),
+            message => message.to_owned(),
+        };
+
+        let outcome = repo_repo
+            .commit_files(&query.branch, &commit, files, &message, &user.username)
+            .await
+            .unwrap();
+
+        match outcome {
+            CommitOutcome::Committed(_) => UploadMessage::Success,
+            CommitOutcome::Unchanged => UploadMessage::Unchanged,
+            CommitOutcome::BranchMoved => UploadMessage::BranchMoved,
+            CommitOutcome::TooLarge(_) => UploadMessage::TooLarge,
+            CommitOutcome::LfsTracked(_) => UploadMessage::LfsTracked,
+            CommitOutcome::InvalidPath(_) => UploadMessage::InvalidPath,
+            CommitOutcome::UnknownBranch => return Err(StatusTemplate(StatusCode::NOT_FOUND)),
+        }
+    };
+
+    cookies.add(Cookie::new(COOKIE_MESSAGE, message.as_ref()));
+
+    Ok(SetCookies::new(
+        redirect::to_repo_tree(&dir.user, &dir.repo, path, &query.branch),
+        cookies,
+    ))
+}
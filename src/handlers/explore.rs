@@ -0,0 +1,81 @@
+use axum::extract::Query;
+use serde::Deserialize;
+use tracing::{info, instrument};
+
+use crate::{
+    extract::User,
+    repositories::UserRepository,
+    templates::{self, ExploreSort},
+};
+
+/// Amount of repos shown per page.
+const PAGE_SIZE: usize = 20;
+
+#[derive(Deserialize)]
+pub struct ExploreQuery {
+    #[serde(default)]
+    pub q: String,
+    #[serde(default)]
+    pub sort: ExploreSort,
+    #[serde(default)]
+    pub page: usize,
+}
+
+#[instrument(skip_all, fields(?user.username, ?query.q))]
+pub async fn index(User(user): User, Query(query): Query<ExploreQuery>) -> templates::Explore {
+    info!("got explore request");
+
+    let search = query.q.trim().to_lowercase();
+    let matches = |value: &str| value.to_lowercase().contains(&search);
+
+    let mut users = UserRepository::for_user(&user.username)
+        .list_user_names(&user.username)
+        .await
+        .unwrap();
+    users.sort();
+
+    let mut repos = Vec::new();
+    for name in &users {
+        let user_repos = UserRepository::for_user(name)
+            .list_repos(&user.username)
+            .await
+            .unwrap();
+
+        repos.extend(user_repos.into_iter().filter(|repo| {
+            matches(&repo.user) || matches(&repo.name) || matches(&repo.description)
+        }));
+    }
+
+    repos.sort_by(|a, b| (a.name.to_lowercase(), &a.user).cmp(&(b.name.to_lowercase(), &b.user)));
+    if query.sort == ExploreSort::Pushed {
+        // Newest first, with repos that were never pushed to at the end. The sort is stable, so
+        // repos with the same push time stay sorted by name.
+        repos.sort_by_key(|repo| std::cmp::Reverse(repo.pushed));
+    }
+
+    let total = repos.len();
+    let pages = total.div_ceil(PAGE_SIZE).max(1);
+    let page = query.page.clamp(1, pages);
+    let repos = repos
+        .into_iter()
+        .skip((page - 1) * PAGE_SIZE)
+        .take(PAGE_SIZE)
+        .collect();
+
+    let users = if search.is_empty() {
+        Vec::new()
+    } else {
+        users.into_iter().filter(|name| matches(name)).collect()
+    };
+
+    templates::Explore {
+        auth_user: Some(user),
+        query: query.q.trim().to_owned(),
+        sort: query.sort,
+        page,
+        pages,
+        total,
+        users,
+        repos,
+    }
+}
@@ -1,7 +1,4 @@
-use std::{
-    io::{Error as IoError, ErrorKind},
-    process::Stdio,
-};
+use std::{io::Error as IoError, process::Stdio};
 
 use anyhow::Result;
 use axum::{
@@ -18,7 +15,7 @@ use tokio::{fs, process::Command};
 use tokio_util::io::{ReaderStream, StreamReader};
 use tracing::{debug, error, info};
 
-use crate::{dirs::DIRS, extract::BasicAuth};
+use crate::{dirs::DIRS, extract::BasicAuth, repositories::RepoRepository};
 
 #[derive(Clone, Copy, Debug, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -86,6 +83,9 @@ pub async fn info_refs(
     if fs::metadata(&path).await.is_err() {
         return Err(StatusCode::NOT_FOUND);
     }
+    if !may_use(query.service, &auth, &params.user, &params.repo) {
+        return Err(StatusCode::FORBIDDEN);
+    }
 
     let output = Command::new(query.service.command())
         .arg("--advertise-refs")
@@ -136,6 +136,9 @@ pub async fn pack(
     if fs::metadata(&path).await.is_err() {
         return Err(StatusCode::NOT_FOUND);
     }
+    if !may_use(params.service, &auth, &params.user, &params.repo) {
+        return Err(StatusCode::FORBIDDEN);
+    }
 
     let mut process = Command::new(params.service.command())
         .arg("--stateless-rpc")
@@ -154,9 +157,7 @@ pub async fn pack(
     let stdout = process.stdout.take().unwrap();
 
     tokio::spawn(async move {
-        let body = body
-            .into_data_stream()
-            .map_err(|e| IoError::new(ErrorKind::Other, e));
+        let body = body.into_data_stream().map_err(IoError::other);
         let mut body = StreamReader::new(body);
 
         if let Err(error) = tokio::io::copy(&mut body, &mut stdin).await {
@@ -164,13 +165,26 @@ pub async fn pack(
             return;
         }
 
-        if let Err(error) = process.wait().await {
-            error!(?error, "failed completing command");
-        }
+        let succeeded = match process.wait().await {
+            Ok(status) => status.success(),
+            Err(error) => {
+                error!(?error, "failed completing command");
+                false
+            }
+        };
 
         if let Err(error) = adjust_head(&path) {
             error!(?error, "failed adjusting repo head");
         }
+
+        if succeeded
+            && matches!(params.service, GitService::GitReceivePack)
+            && let Err(error) = RepoRepository::for_repo(&params.user, &params.repo)
+                .mark_pushed()
+                .await
+        {
+            error!(?error, "failed recording push time");
+        }
     });
 
     let body = Body::from_stream(ReaderStream::new(stdout));
@@ -184,6 +198,17 @@ pub async fn pack(
     ))
 }
 
+/// Whether the authenticated user may use the service on the repo. Pushes write refs, so they
+/// follow the same rules as ref changes from the web interface.
+fn may_use(service: GitService, auth: &BasicAuth, user: &str, repo: &str) -> bool {
+    match service {
+        GitService::GitReceivePack => {
+            RepoRepository::for_repo(user, repo).can_write_refs(&auth.username)
+        }
+        GitService::GitUploadPack => true,
+    }
+}
+
 fn adjust_head(path: &Utf8Path) -> Result<()> {
     let repo = Repository::open(path)?;
     if repo.head().is_ok() {
@@ -8,8 +8,14 @@ use crate::{extract::User, response::StatusTemplate, templates};
 pub mod admin;
 pub mod assets;
 pub mod auth;
+pub mod branch;
+pub mod compare;
+pub mod edit;
+pub mod explore;
 pub mod git;
+pub mod release;
 pub mod repo;
+pub mod tag;
 pub mod user;
 
 pub async fn index(user: Option<User>) -> impl IntoResponse {
@@ -0,0 +1,266 @@
+use std::io::Error as IoError;
+
+use axum::{
+    body::Body,
+    extract::{Form, Multipart, Path},
+    http::{HeaderValue, StatusCode, header::CONTENT_DISPOSITION},
+    response::IntoResponse,
+};
+use axum_extra::headers::{ContentLength, ContentType, HeaderMap, HeaderMapExt};
+use camino::Utf8PathBuf;
+use futures_util::TryStreamExt;
+use serde::Deserialize;
+use tokio_util::io::{ReaderStream, StreamReader};
+use tracing::{info, instrument};
+
+use super::repo::BasePath;
+use crate::{
+    cookies::{Cookie, Cookies},
+    extract::User,
+    redirect,
+    render::{self, LinkBase},
+    repositories::{ReleaseRepository, RepoRepository},
+    response::{SetCookies, StatusTemplate},
+    session::COOKIE_MESSAGE,
+    templates::{self, repo::ReleaseMessage},
+    validate,
+};
+
+/// Maximum size of a single upload request for release assets, currently set to **1 GiB**.
+pub const ASSET_UPLOAD_LIMIT: usize = 1024 * 1024 * 1024;
+
+#[instrument(skip_all, fields(?path.user, ?path.repo))]
+pub async fn index(
+    User(user): User,
+    Path(path): Path<BasePath>,
+    mut cookies: Cookies,
+) -> Result<impl IntoResponse, StatusTemplate> {
+    info!("got release index request");
+
+    let repo_repo = RepoRepository::for_repo(&path.user, &path.repo);
+
+    if !repo_repo.exists().await || !repo_repo.visible(&user.username, &path.user).await.unwrap() {
+        return Err(StatusTemplate(StatusCode::NOT_FOUND));
+    }
+
+    let message = cookies
+        .get(COOKIE_MESSAGE)
+        .and_then(|cookie| cookie.value().parse().ok());
+
+    if message.is_some() {
+        cookies.remove(COOKIE_MESSAGE);
+    }
+
+    let branch = repo_repo.get_branch().await.unwrap();
+    let releases = ReleaseRepository::for_repo(&path.user, &path.repo)
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|release| {
+            let base = LinkBase {
+                user: path.user.clone(),
+                repo: path.repo.clone(),
+                rev: release.tag.clone(),
+                dir: Utf8PathBuf::new(),
+            };
+            let notes = render::markdown(&release.notes, &base).html;
+            (release, notes)
+        })
+        .collect();
+
+    Ok(SetCookies::new(
+        templates::repo::Releases {
+            auth_user: Some(user),
+            message,
+            user: path.user,
+            repo: path.repo,
+            branch,
+            releases,
+        },
+        cookies,
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct ReleasePath {
+    pub user: String,
+    #[serde(deserialize_with = "crate::de::repo_name")]
+    pub repo: String,
+    pub tag: String,
+}
+
+#[derive(Deserialize)]
+pub struct Notes {
+    notes: String,
+}
+
+#[instrument(skip_all, fields(?path.user, ?path.repo, ?path.tag))]
+pub async fn notes_post(
+    User(user): User,
+    Path(path): Path<ReleasePath>,
+    mut cookies: Cookies,
+    Form(notes): Form<Notes>,
+) -> Result<impl IntoResponse, StatusTemplate> {
+    info!("got release notes request");
+
+    let repo_repo = RepoRepository::for_repo(&path.user, &path.repo);
+    let release_repo = ReleaseRepository::for_repo(&path.user, &path.repo);
+
+    if user.username != path.user || !repo_repo.exists().await {
+        return Err(StatusTemplate(StatusCode::NOT_FOUND));
+    }
+
+    let message = if release_repo.tag_exists(&path.tag).await.unwrap() {
+        let mut current = release_repo.load_info(&path.tag).await.unwrap();
+        if current.notes != notes.notes {
+            current.notes = notes.notes;
+            release_repo.save_info(&path.tag, &current).await.unwrap();
+        }
+
+        ReleaseMessage::Success
+    } else {
+        ReleaseMessage::UnknownTag
+    };
+
+    cookies.add(Cookie::new(COOKIE_MESSAGE, message.as_ref()));
+
+    Ok(SetCookies::new(
+        redirect::to_repo_releases(&path.user, &path.repo),
+        cookies,
+    ))
+}
+
+#[instrument(skip_all, fields(?path.user, ?path.repo, ?path.tag))]
+pub async fn asset_post(
+    User(user): User,
+    Path(path): Path<ReleasePath>,
+    mut cookies: Cookies,
+    mut multipart: Multipart,
+) -> Result<impl IntoResponse, StatusTemplate> {
+    info!("got release asset upload request");
+
+    let repo_repo = RepoRepository::for_repo(&path.user, &path.repo);
+    let release_repo = ReleaseRepository::for_repo(&path.user, &path.repo);
+
+    if user.username != path.user || !repo_repo.exists().await {
+        return Err(StatusTemplate(StatusCode::NOT_FOUND));
+    }
+
+    let message = if release_repo.tag_exists(&path.tag).await.unwrap() {
+        let mut message = ReleaseMessage::Success;
+
+        while let Some(field) = multipart
+            .next_field()
+            .await
+            .map_err(|e| StatusTemplate(e.status()))?
+        {
+            // Browsers send an empty file name if no file was selected at all.
+            let name = match field.file_name() {
+                Some(name) if !name.is_empty() => name.to_owned(),
+                _ => continue,
+            };
+
+            if !validate::asset_name(&name) {
+                message = ReleaseMessage::InvalidAssetName;
+                break;
+            }
+
+            let content = StreamReader::new(field.map_err(IoError::other));
+            release_repo
+                .add_asset(&path.tag, &name, content)
+                .await
+                .map_err(|_| StatusTemplate(StatusCode::BAD_REQUEST))?;
+        }
+
+        message
+    } else {
+        ReleaseMessage::UnknownTag
+    };
+
+    cookies.add(Cookie::new(COOKIE_MESSAGE, message.as_ref()));
+
+    Ok(SetCookies::new(
+        redirect::to_repo_releases(&path.user, &path.repo),
+        cookies,
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct AssetPath {
+    pub user: String,
+    #[serde(deserialize_with = "crate::de::repo_name")]
+    pub repo: String,
+    pub tag: String,
+    pub name: String,
+}
+
+#[instrument(skip_all, fields(?path.user, ?path.repo, ?path.tag, ?path.name))]
+pub async fn asset_delete_post(
+    User(user): User,
+    Path(path): Path<AssetPath>,
+    mut cookies: Cookies,
+) -> Result<impl IntoResponse, StatusTemplate> {
+    info!("got release asset delete request");
+
+    let repo_repo = RepoRepository::for_repo(&path.user, &path.repo);
+
+    if user.username != path.user || !repo_repo.exists().await || !validate::asset_name(&path.name)
+    {
+        return Err(StatusTemplate(StatusCode::NOT_FOUND));
+    }
+
+    let deleted = ReleaseRepository::for_repo(&path.user, &path.repo)
+        .delete_asset(&path.tag, &path.name)
+        .await
+        .unwrap();
+
+    if !deleted {
+        return Err(StatusTemplate(StatusCode::NOT_FOUND));
+    }
+
+    cookies.add(Cookie::new(
+        COOKIE_MESSAGE,
+        ReleaseMessage::Success.as_ref(),
+    ));
+
+    Ok(SetCookies::new(
+        redirect::to_repo_releases(&path.user, &path.repo),
+        cookies,
+    ))
+}
+
+#[instrument(skip_all, fields(?path.user, ?path.repo, ?path.tag, ?path.name))]
+pub async fn download(
+    User(user): User,
+    Path(path): Path<AssetPath>,
+) -> Result<impl IntoResponse, StatusTemplate> {
+    info!("got release asset download request");
+
+    let repo_repo = RepoRepository::for_repo(&path.user, &path.repo);
+
+    if !repo_repo.exists().await
+        || !repo_repo.visible(&user.username, &path.user).await.unwrap()
+        || !validate::asset_name(&path.name)
+    {
+        return Err(StatusTemplate(StatusCode::NOT_FOUND));
+    }
+
+    let (file, size) = ReleaseRepository::for_repo(&path.user, &path.repo)
+        .open_asset(&path.tag, &path.name)
+        .await
+        .unwrap()
+        .ok_or(StatusTemplate(StatusCode::NOT_FOUND))?;
+
+    let mut headers = HeaderMap::with_capacity(3);
+    headers.typed_insert(ContentType::octet_stream());
+    headers.typed_insert(ContentLength(size));
+    // Unwrap: asset names are validated to only contain ASCII characters and spaces that are safe
+    // to use inside a quoted string.
+    headers.insert(
+        CONTENT_DISPOSITION,
+        HeaderValue::try_from(format!("attachment; filename=\"{}\"", path.name)).unwrap(),
+    );
+
+    Ok((headers, Body::from_stream(ReaderStream::new(file))))
+}
@@ -1,27 +1,31 @@
-use std::sync::LazyLock;
+use std::{convert::Infallible, mem, ops::Bound};
 
 use axum::{
+    body::Body,
     extract::{Form, Path, Query},
-    http::StatusCode,
-    response::IntoResponse,
+    http::{HeaderValue, StatusCode, header::CONTENT_SECURITY_POLICY},
+    response::{IntoResponse, Response},
+};
+use axum_extra::{
+    TypedHeader,
+    headers::{
+        AcceptRanges, ContentLength, ContentRange, ContentType, HeaderMap, HeaderMapExt, Range,
+    },
 };
 use camino::{Utf8Path, Utf8PathBuf};
-use pulldown_cmark::{CodeBlockKind, Event, Tag, TagEnd};
+use futures_util::stream;
 use serde::Deserialize;
-use syntect::{
-    html::{ClassStyle, ClassedHTMLGenerator},
-    parsing::{SyntaxDefinition, SyntaxReference, SyntaxSet},
-    util::LinesWithEndings,
-};
 use tracing::{info, instrument};
 
 use crate::{
     cookies::{Cookie, Cookies},
     extract::User,
-    models::TreeKind,
+    models::{self, MediaKind, RepoFile, TreeKind, UserAccount},
     redirect,
-    repositories::{RepoRepository, UserRepository},
+    render::{self, LinkBase},
+    repositories::{RepoRepository, SettingsRepository, UserRepository},
     response::{SetCookies, StatusTemplate},
+    scaffold::{Gitignore, License, Scaffold},
     session::{COOKIE_ERROR, COOKIE_MESSAGE},
     templates, validate,
 };
@@ -45,15 +49,24 @@ pub async fn index(
     if repo_repo.exists().await && repo_repo.visible(&user.username, &path.user).await.unwrap() {
         let files = {
             let mut files = repo_repo.get_file_list().await.unwrap();
-            files.sort_by_key(|file| file.kind);
+            sort_files(&mut files);
             files
         };
 
         let branch = repo_repo.get_branch().await.unwrap();
-        let readme = repo_repo.get_readme().await.unwrap().map_or_else(
-            || "No project readme available".to_owned(),
-            |readme| render_markdown(&readme),
-        );
+        let base = LinkBase {
+            user: path.user.clone(),
+            repo: path.repo.clone(),
+            rev: branch.clone(),
+            dir: Utf8PathBuf::new(),
+        };
+        let (readme, toc) = match repo_repo.get_readme().await.unwrap() {
+            Some(readme) => {
+                let document = render::readme(&readme, &base);
+                (document.html, document.toc)
+            }
+            None => ("No project readme available".to_owned(), Vec::new()),
+        };
 
         Ok(templates::repo::Index {
             auth_user: Some(user),
@@ -62,6 +75,7 @@ pub async fn index(
             branch,
             files,
             readme,
+            toc,
         })
     } else {
         Err(StatusTemplate(StatusCode::NOT_FOUND))
@@ -76,9 +90,18 @@ pub struct Tree {
     pub path: Option<String>,
 }
 
+/// Query for tree and raw routes. Despite its name, `branch` takes any revspec that git
+/// understands, like tags, commit IDs or relative references such as `main~3`.
 #[derive(Deserialize)]
 pub struct TreeQuery {
     pub branch: String,
+    /// Show the source of files that are rendered by default, like Markdown.
+    #[serde(default, deserialize_with = "crate::de::form_bool")]
+    pub source: bool,
+    /// Range of lines to highlight in the code view. The `#L42-L60` fragment is never sent to the
+    /// server, so it only scrolls to the range that is selected with this query.
+    #[serde(default, deserialize_with = "crate::de::line_range")]
+    pub lines: Option<(usize, usize)>,
 }
 
 #[instrument(skip_all, fields(?user.username, ?tree.user, ?tree.repo, ?tree.path, ?query.branch))]
@@ -86,45 +109,296 @@ pub async fn tree(
     User(user): User,
     Path(tree): Path<Tree>,
     Query(query): Query<TreeQuery>,
+    mut cookies: Cookies,
 ) -> Result<impl IntoResponse, StatusTemplate> {
     info!("got repo tree request");
 
     let repo_repo = RepoRepository::for_repo(&tree.user, &tree.repo);
 
     if repo_repo.exists().await && repo_repo.visible(&user.username, &tree.user).await.unwrap() {
+        let message = cookies
+            .get(COOKIE_MESSAGE)
+            .and_then(|cookie| cookie.value().parse().ok());
+
+        if message.is_some() {
+            cookies.remove(COOKIE_MESSAGE);
+        }
+
         let branches = repo_repo.list_branches().await.unwrap();
+        let tags = repo_repo.list_tags().await.unwrap();
+        let commit = repo_repo
+            .resolve_commit(&query.branch)
+            .await
+            .unwrap()
+            .ok_or(StatusTemplate(StatusCode::NOT_FOUND))?;
+        let mut readme_html = None;
+        let mut lines = Vec::new();
+        let mut outline = Vec::new();
         let repo_tree = {
             let path = tree.path.as_ref().map(Utf8Path::new);
-            let tree = repo_repo.get_tree_list(&query.branch, path).await.unwrap();
-            let mut tree = tree.ok_or(StatusTemplate(StatusCode::NOT_FOUND))?;
-
-            match &mut tree.kind {
-                TreeKind::Directory(files) => {
-                    files.sort_by_key(|file| file.kind);
+            let repo_tree = repo_repo.get_tree_list(&query.branch, path).await.unwrap();
+            let mut repo_tree = repo_tree.ok_or(StatusTemplate(StatusCode::NOT_FOUND))?;
+
+            let mut base = LinkBase {
+                user: tree.user.clone(),
+                repo: tree.repo.clone(),
+                rev: query.branch.clone(),
+                dir: path.map(ToOwned::to_owned).unwrap_or_default(),
+            };
+
+            match &mut repo_tree.kind {
+                TreeKind::Directory { files, readme } => {
+                    sort_files(files);
+
+                    if let Some(readme) = readme.take() {
+                        readme_html = Some(
+                            tokio::task::spawn_blocking(move || render::readme(&readme, &base))
+                                .await
+                                .unwrap()
+                                .html,
+                        );
+                    }
+                }
+                TreeKind::Text(text) if !query.source && models::is_markdown(&repo_tree.name) => {
+                    let content = mem::take(text);
+                    base.dir.pop();
+
+                    let document =
+                        tokio::task::spawn_blocking(move || render::markdown(&content, &base))
+                            .await
+                            .unwrap();
+
+                    repo_tree.kind = TreeKind::Markdown {
+                        html: document.html,
+                        toc: document.toc,
+                    };
                 }
                 TreeKind::Text(text) => {
-                    if let Some(ext) = Utf8Path::new(&tree.name).extension() {
-                        let syntax = SYNTAX_SET
-                            .find_syntax_by_extension(ext)
-                            .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text());
-
-                        *text = highlight_code(text, syntax).unwrap();
-                    }
+                    let content = mem::take(text);
+
+                    let code = if content.len() <= HIGHLIGHT_LIMIT {
+                        let language = match path {
+                            Some(path) => {
+                                repo_repo.get_language(&query.branch, path).await.unwrap()
+                            }
+                            None => None,
+                        };
+                        let name = repo_tree.name.clone();
+
+                        tokio::task::spawn_blocking(move || {
+                            let syntax =
+                                render::detect_syntax(&name, &content, language.as_deref());
+                            // Custom syntaxes may be broken, so fall back to plain text.
+                            render::highlight_lines(&content, syntax).unwrap_or_else(|_| {
+                                render::Code {
+                                    lines: render::escape_lines(&content),
+                                    outline: Vec::new(),
+                                }
+                            })
+                        })
+                        .await
+                        .unwrap()
+                    } else {
+                        render::Code {
+                            lines: render::escape_lines(&content),
+                            outline: Vec::new(),
+                        }
+                    };
+
+                    lines = code.lines;
+                    outline = code.outline;
                 }
-                TreeKind::Binary(_) => {}
+                TreeKind::Truncated { preview, .. } => {
+                    lines = render::escape_lines(&mem::take(preview));
+                }
+                TreeKind::Markdown { .. }
+                | TreeKind::Binary(_)
+                | TreeKind::Media { .. }
+                | TreeKind::TooLarge(_) => {}
             }
 
-            tree
+            repo_tree
+        };
+
+        Ok(SetCookies::new(
+            templates::repo::Tree {
+                message,
+                auth_user: Some(user),
+                user: tree.user,
+                repo: tree.repo,
+                branch: query.branch,
+                branches,
+                tags,
+                path: tree.path.map(Utf8PathBuf::from).unwrap_or_default(),
+                tree: repo_tree,
+                readme: readme_html,
+                lines,
+                outline,
+                source: query.source,
+                selected: query.lines,
+                commit,
+            },
+            cookies,
+        ))
+    } else {
+        Err(StatusTemplate(StatusCode::NOT_FOUND))
+    }
+}
+
+/// Sort directories and submodules before all kinds of files, each group by name.
+fn sort_files(files: &mut [RepoFile]) {
+    files.sort_by(|a, b| {
+        b.kind
+            .is_directory_like()
+            .cmp(&a.kind.is_directory_like())
+            .then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+#[derive(Deserialize)]
+pub struct Raw {
+    pub user: String,
+    #[serde(deserialize_with = "crate::de::repo_name")]
+    pub repo: String,
+    pub path: String,
+}
+
+#[instrument(skip_all, fields(?user.username, ?raw.user, ?raw.repo, ?raw.path, ?query.branch))]
+pub async fn raw(
+    User(user): User,
+    Path(raw): Path<Raw>,
+    Query(query): Query<TreeQuery>,
+    range: Option<TypedHeader<Range>>,
+) -> Result<Response, StatusTemplate> {
+    info!("got repo raw request");
+
+    let repo_repo = RepoRepository::for_repo(&raw.user, &raw.repo);
+
+    if !repo_repo.exists().await || !repo_repo.visible(&user.username, &raw.user).await.unwrap() {
+        return Err(StatusTemplate(StatusCode::NOT_FOUND));
+    }
+
+    let requested = range.is_some();
+    let blob = repo_repo
+        .get_raw_blob(&query.branch, Utf8Path::new(&raw.path), move |size| {
+            range.and_then(|TypedHeader(range)| byte_range(&range, size))
+        })
+        .await
+        .unwrap()
+        .ok_or(StatusTemplate(StatusCode::NOT_FOUND))?;
+
+    let media = MediaKind::detect(&raw.path);
+    let content_type = if let Some((_, media_type)) = media {
+        // Unwrap: all detected media types are valid MIME types.
+        media_type.parse().unwrap()
+    } else if blob.text {
+        mime::TEXT_PLAIN_UTF_8
+    } else {
+        mime::APPLICATION_OCTET_STREAM
+    };
+
+    let mut headers = HeaderMap::with_capacity(5);
+    headers.typed_insert(ContentType::from(content_type));
+    headers.typed_insert(AcceptRanges::bytes());
+
+    // SVGs can contain scripts, which must never run on our origin when the file is opened
+    // directly instead of being embedded as an image.
+    if media.is_some_and(|(_, media_type)| media_type == "image/svg+xml") {
+        headers.insert(CONTENT_SECURITY_POLICY, HeaderValue::from_static("sandbox"));
+    }
+
+    let status = match blob.range {
+        Some((start, end)) => {
+            // Unwrap: the range is always within the size of the blob.
+            headers.typed_insert(ContentRange::bytes(start..=end, blob.size).unwrap());
+            headers.typed_insert(ContentLength(end - start + 1));
+            StatusCode::PARTIAL_CONTENT
+        }
+        None if requested => {
+            headers.typed_insert(ContentRange::unsatisfied_bytes(blob.size));
+            return Ok((StatusCode::RANGE_NOT_SATISFIABLE, headers).into_response());
+        }
+        None => {
+            headers.typed_insert(ContentLength(blob.size));
+            StatusCode::OK
+        }
+    };
+
+    let mut content = blob.content;
+    let content = stream::poll_fn(move |cx| {
+        content
+            .poll_recv(cx)
+            .map(|chunk| chunk.map(Ok::<_, Infallible>))
+    });
+
+    Ok((status, headers, Body::from_stream(content)).into_response())
+}
+
+/// Select the first satisfiable range of a `Range` header, as an inclusive range of bytes within
+/// the given size. Multiple ranges aren't supported, so only the first one is sent.
+fn byte_range(range: &Range, size: u64) -> Option<(u64, u64)> {
+    let last = size.checked_sub(1)?;
+
+    range.satisfiable_ranges(size).find_map(|(start, end)| {
+        let start = match start {
+            Bound::Included(start) => start,
+            Bound::Excluded(start) => start.checked_add(1)?,
+            Bound::Unbounded => 0,
+        };
+        let end = match end {
+            Bound::Included(end) => end.min(last),
+            Bound::Excluded(end) => end.checked_sub(1)?.min(last),
+            Bound::Unbounded => last,
+        };
+
+        (start <= end).then_some((start, end))
+    })
+}
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    /// Revspec to search in, defaulting to the default branch of the repo.
+    pub branch: Option<String>,
+    #[serde(default)]
+    pub q: String,
+}
+
+#[instrument(skip_all, fields(?user.username, ?path.user, ?path.repo, ?query.branch, ?query.q))]
+pub async fn search(
+    User(user): User,
+    Path(path): Path<BasePath>,
+    Query(query): Query<SearchQuery>,
+) -> Result<impl IntoResponse, StatusTemplate> {
+    info!("got repo search request");
+
+    let repo_repo = RepoRepository::for_repo(&path.user, &path.repo);
+
+    if repo_repo.exists().await && repo_repo.visible(&user.username, &path.user).await.unwrap() {
+        let branch = match query.branch {
+            Some(branch) => branch,
+            None => repo_repo.get_branch().await.unwrap(),
+        };
+        let search = query.q.trim();
+
+        let results = if search.is_empty() {
+            None
+        } else {
+            Some(
+                repo_repo
+                    .search(&branch, search)
+                    .await
+                    .unwrap()
+                    .ok_or(StatusTemplate(StatusCode::NOT_FOUND))?,
+            )
         };
 
-        Ok(templates::repo::Tree {
+        Ok(templates::repo::Search {
             auth_user: Some(user),
-            user: tree.user,
-            repo: tree.repo,
-            branch: query.branch,
-            branches,
-            path: tree.path.map(Utf8PathBuf::from).unwrap_or_default(),
-            tree: repo_tree,
+            user: path.user,
+            repo: path.repo,
+            branch,
+            query: search.to_owned(),
+            results,
         })
     } else {
         Err(StatusTemplate(StatusCode::NOT_FOUND))
@@ -143,7 +417,42 @@ pub async fn create(User(user): User, mut cookies: Cookies) -> impl IntoResponse
         cookies.remove(COOKIE_ERROR);
     }
 
-    SetCookies::new(templates::repo::Create { error }, cookies)
+    let users = UserRepository::for_user(&user.username)
+        .list_user_names(&user.username)
+        .await
+        .unwrap();
+
+    let mut repo_templates = Vec::new();
+    for name in &users {
+        let user_repos = UserRepository::for_user(name)
+            .list_repos(&user.username)
+            .await
+            .unwrap();
+
+        repo_templates.extend(user_repos.into_iter().filter(|repo| repo.template));
+    }
+
+    repo_templates.sort_by(|a, b| (&a.user, &a.name).cmp(&(&b.user, &b.name)));
+
+    let default_branch = default_branch(&user).await;
+
+    SetCookies::new(
+        templates::repo::Create {
+            auth_user: Some(user),
+            error,
+            default_branch,
+            templates: repo_templates,
+        },
+        cookies,
+    )
+}
+
+/// Initial branch name of the user's new repos, either set by the user or server-wide.
+async fn default_branch(user: &UserAccount) -> Option<String> {
+    match &user.default_branch {
+        Some(branch) => Some(branch.clone()),
+        None => SettingsRepository::new().get_default_branch().await,
+    }
 }
 
 #[derive(Deserialize)]
@@ -152,6 +461,18 @@ pub struct Create {
     description: String,
     #[serde(default, deserialize_with = "crate::de::form_bool")]
     private: bool,
+    /// Name of the initial branch, using the default of the user or server if empty.
+    #[serde(default)]
+    branch: String,
+    #[serde(default, deserialize_with = "crate::de::form_bool")]
+    readme: bool,
+    #[serde(default, deserialize_with = "crate::de::empty_as_none")]
+    license: Option<License>,
+    #[serde(default, deserialize_with = "crate::de::empty_as_none")]
+    gitignore: Option<Gitignore>,
+    /// Template repo to copy, as `<user>/<repo>`. Takes precedence over the initial files.
+    #[serde(default, deserialize_with = "crate::de::empty_as_none")]
+    template: Option<String>,
 }
 
 #[instrument(skip_all, fields(?user.username, ?create.name))]
@@ -170,14 +491,60 @@ pub async fn create_post(
         return SetCookies::new(redirect::to_repo_create(), cookies);
     }
 
+    let branch = match create.branch.trim() {
+        "" => default_branch(&user).await,
+        branch if validate::branch_name(branch) => Some(branch.to_owned()),
+        _ => {
+            cookies.add(Cookie::new(
+                COOKIE_ERROR,
+                templates::repo::RepoCreateError::InvalidBranchName.as_ref(),
+            ));
+            return SetCookies::new(redirect::to_repo_create(), cookies);
+        }
+    };
+
+    let template = create
+        .template
+        .as_deref()
+        .map(|template| template.split_once('/').unwrap_or((template, "")));
+
+    if let Some((template_user, template_repo)) = template
+        && !is_template(&user.username, template_user, template_repo).await
+    {
+        cookies.add(Cookie::new(
+            COOKIE_ERROR,
+            templates::repo::RepoCreateError::UnknownTemplate.as_ref(),
+        ));
+        return SetCookies::new(redirect::to_repo_create(), cookies);
+    }
+
+    let scaffold = Scaffold {
+        readme: create.readme,
+        license: create.license,
+        gitignore: create.gitignore,
+    };
+    let files = scaffold.files(&create.name, &create.description, &user.username);
+
     let user_repo = UserRepository::for_user(&user.username);
-    let created = user_repo
-        .repo(&create.name)
-        .create(create.description, create.private)
+    let repo_repo = user_repo.repo(&create.name);
+    let created = repo_repo
+        .create(create.description, create.private, branch)
         .await
         .unwrap();
 
     if created {
+        if let Some((template_user, template_repo)) = template {
+            repo_repo
+                .copy_from(template_user, template_repo)
+                .await
+                .unwrap();
+        } else if !scaffold.is_empty() {
+            repo_repo
+                .commit_initial(files, "Initial commit", &user.username)
+                .await
+                .unwrap();
+        }
+
         SetCookies::new(
             redirect::to_repo_index(&user.username, &create.name),
             cookies,
@@ -191,6 +558,19 @@ pub async fn create_post(
     }
 }
 
+/// Check whether a repo exists, is visible to the user and is marked as template.
+async fn is_template(auth_user: &str, user: &str, repo: &str) -> bool {
+    if !validate::username(user) || !validate::repository(repo) {
+        return false;
+    }
+
+    let repo_repo = RepoRepository::for_repo(user, repo);
+
+    repo_repo.exists().await
+        && repo_repo.visible(auth_user, user).await.unwrap()
+        && repo_repo.load_info().await.unwrap().template
+}
+
 #[instrument(skip_all, fields(?path.user, ?path.repo))]
 pub async fn delete(
     User(user): User,
@@ -204,7 +584,10 @@ pub async fn delete(
         return Err(StatusTemplate(StatusCode::NOT_FOUND));
     }
 
-    Ok(templates::repo::Delete { repo: path.repo })
+    Ok(templates::repo::Delete {
+        auth_user: Some(user),
+        repo: path.repo,
+    })
 }
 
 #[instrument(skip_all, fields(?path.user, ?path.repo))]
@@ -248,7 +631,7 @@ pub async fn settings(
     }
 
     let branch = repo_repo.get_branch().await.unwrap();
-    let branches = repo_repo.list_branches().await.unwrap();
+    let branches = repo_repo.list_branch_info().await.unwrap();
     let settings = repo_repo.load_info().await.unwrap();
 
     Ok(SetCookies::new(
@@ -271,6 +654,8 @@ pub struct Settings {
     branch: Option<String>,
     #[serde(default, deserialize_with = "crate::de::form_bool")]
     private: bool,
+    #[serde(default, deserialize_with = "crate::de::form_bool")]
+    template: bool,
 }
 
 #[instrument(skip_all, fields(?path.user, ?path.repo))]
@@ -295,12 +680,14 @@ pub async fn settings_post(
         }
     }
 
-    let mut current = repo_repo.load_info().await.unwrap();
-    if current.description != settings.description || current.private != settings.private {
-        current.description = settings.description;
-        current.private = settings.private;
-        repo_repo.save_info(&current).await.unwrap();
-    }
+    repo_repo
+        .update_info(|current| {
+            current.description = settings.description;
+            current.private = settings.private;
+            current.template = settings.template;
+        })
+        .await
+        .unwrap();
 
     cookies.add(Cookie::new(
         COOKIE_MESSAGE,
@@ -313,71 +700,5 @@ pub async fn settings_post(
     ))
 }
 
-static SYNTAX_SET: LazyLock<SyntaxSet> = LazyLock::new(|| {
-    let mut builder = SyntaxSet::load_defaults_newlines().into_builder();
-    builder.add(
-        SyntaxDefinition::load_from_str(
-            include_str!("../../assets/TOML.sublime-syntax"),
-            true,
-            Some("TOML"),
-        )
-        .unwrap(),
-    );
-    builder.build()
-});
-
-#[allow(clippy::option_if_let_else)]
-#[instrument(skip_all)]
-fn render_markdown(text: &str) -> String {
-    let default_syntax = SYNTAX_SET.find_syntax_plain_text();
-    let mut syntax = None;
-
-    let parser =
-        pulldown_cmark::Parser::new_ext(text, pulldown_cmark::Options::all()).map(|event| {
-            match event {
-                Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang))) => {
-                    syntax = Some(
-                        SYNTAX_SET
-                            .find_syntax_by_token(&lang)
-                            .unwrap_or(default_syntax),
-                    );
-                    Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang)))
-                }
-                Event::Text(text) => {
-                    if let Some(syntax) = syntax {
-                        Event::Html(highlight_code(&text, syntax).unwrap().into())
-                    } else {
-                        Event::Text(text)
-                    }
-                }
-                Event::End(TagEnd::CodeBlock) => {
-                    syntax = None;
-                    Event::End(TagEnd::CodeBlock)
-                }
-                Event::Html(html) => Event::Text(html),
-                event => event,
-            }
-        });
-
-    let mut html = String::new();
-    pulldown_cmark::html::push_html(&mut html, parser);
-
-    html
-}
-
-#[instrument(skip_all)]
-fn highlight_code(text: &str, syntax: &SyntaxReference) -> Result<String, syntect::Error> {
-    let mut generator = ClassedHTMLGenerator::new_with_class_style(
-        syntax,
-        &SYNTAX_SET,
-        ClassStyle::SpacedPrefixed {
-            prefix: "highlight-",
-        },
-    );
-
-    for line in LinesWithEndings::from(text) {
-        generator.parse_html_for_line_which_includes_newline(line)?;
-    }
-
-    Ok(generator.finalize())
-}
+/// Maximum size of text files that are syntax highlighted. Larger files are shown as plain text.
+const HIGHLIGHT_LIMIT: usize = 256 * 1024;
@@ -0,0 +1,131 @@
+use axum::{
+    extract::{Form, Path, Query},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde::Deserialize;
+use tracing::{info, instrument};
+
+use super::repo::BasePath;
+use crate::{
+    cookies::{Cookie, Cookies},
+    extract::User,
+    models::{TagCreateOutcome, TagDeleteOutcome},
+    redirect,
+    repositories::{ReleaseRepository, RepoRepository},
+    response::{SetCookies, StatusTemplate},
+    session::COOKIE_MESSAGE,
+    templates::{self, repo::ReleaseMessage},
+    validate,
+};
+
+#[derive(Deserialize)]
+pub struct TagCreate {
+    name: String,
+    /// Revspec of the commit that the new tag points to.
+    from: String,
+    /// Message of an annotated tag. Lightweight tags are created if it's left empty.
+    message: String,
+}
+
+#[instrument(skip_all, fields(?path.user, ?path.repo, ?create.name, ?create.from))]
+pub async fn create_post(
+    User(user): User,
+    Path(path): Path<BasePath>,
+    mut cookies: Cookies,
+    Form(create): Form<TagCreate>,
+) -> Result<impl IntoResponse, StatusTemplate> {
+    info!("got tag create request");
+
+    let repo_repo = RepoRepository::for_repo(&path.user, &path.repo);
+
+    if !repo_repo.can_write_refs(&user.username) || !repo_repo.exists().await {
+        return Err(StatusTemplate(StatusCode::NOT_FOUND));
+    }
+
+    let message = if validate::tag_name(&create.name) {
+        let tag_message = Some(create.message.trim()).filter(|message| !message.is_empty());
+
+        match repo_repo
+            .create_tag(&create.name, &create.from, tag_message, &user.username)
+            .await
+            .unwrap()
+        {
+            TagCreateOutcome::Created => ReleaseMessage::TagCreated,
+            TagCreateOutcome::AlreadyExists => ReleaseMessage::TagExists,
+            TagCreateOutcome::UnknownRev => ReleaseMessage::UnknownRev,
+        }
+    } else {
+        ReleaseMessage::InvalidTagName
+    };
+
+    cookies.add(Cookie::new(COOKIE_MESSAGE, message.as_ref()));
+
+    Ok(SetCookies::new(
+        redirect::to_repo_releases(&path.user, &path.repo),
+        cookies,
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct TagQuery {
+    pub tag: String,
+}
+
+#[instrument(skip_all, fields(?path.user, ?path.repo, ?query.tag))]
+pub async fn delete(
+    User(user): User,
+    Path(path): Path<BasePath>,
+    Query(query): Query<TagQuery>,
+) -> Result<impl IntoResponse, StatusTemplate> {
+    info!("got tag delete request");
+
+    let repo_repo = RepoRepository::for_repo(&path.user, &path.repo);
+
+    if !repo_repo.can_write_refs(&user.username) || !repo_repo.exists().await {
+        return Err(StatusTemplate(StatusCode::NOT_FOUND));
+    }
+
+    let release = ReleaseRepository::for_repo(&path.user, &path.repo)
+        .list()
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|release| release.tag == query.tag)
+        .ok_or(StatusTemplate(StatusCode::NOT_FOUND))?;
+
+    Ok(templates::repo::TagDelete {
+        auth_user: Some(user),
+        user: path.user,
+        repo: path.repo,
+        release,
+    })
+}
+
+#[instrument(skip_all, fields(?path.user, ?path.repo, ?query.tag))]
+pub async fn delete_post(
+    User(user): User,
+    Path(path): Path<BasePath>,
+    Query(query): Query<TagQuery>,
+    mut cookies: Cookies,
+) -> Result<impl IntoResponse, StatusTemplate> {
+    info!("got tag delete request");
+
+    let repo_repo = RepoRepository::for_repo(&path.user, &path.repo);
+
+    if !repo_repo.can_write_refs(&user.username) || !repo_repo.exists().await {
+        return Err(StatusTemplate(StatusCode::NOT_FOUND));
+    }
+
+    let message = match repo_repo.delete_tag(&query.tag).await.unwrap() {
+        TagDeleteOutcome::Deleted => ReleaseMessage::TagDeleted,
+        TagDeleteOutcome::NotFound => ReleaseMessage::UnknownTag,
+    };
+
+    cookies.add(Cookie::new(COOKIE_MESSAGE, message.as_ref()));
+
+    Ok(SetCookies::new(
+        redirect::to_repo_releases(&path.user, &path.repo),
+        cookies,
+    ))
+}
@@ -9,8 +9,9 @@ use tracing::{info, instrument};
 use crate::{
     cookies::{Cookie, Cookies},
     extract::User,
+    models::Theme,
     redirect,
-    repositories::UserRepository,
+    repositories::{SettingsRepository, UserRepository},
     response::{SetCookies, StatusTemplate},
     session::COOKIE_MESSAGE,
     templates, validate,
@@ -88,6 +89,7 @@ pub async fn settings(
             message,
             user: path.user,
             settings,
+            server_branch: SettingsRepository::new().get_default_branch().await,
         },
         cookies,
     ))
@@ -98,6 +100,10 @@ pub struct Settings {
     description: String,
     #[serde(default, deserialize_with = "crate::de::form_bool")]
     private: bool,
+    #[serde(default)]
+    theme: Theme,
+    #[serde(default)]
+    default_branch: String,
 }
 
 #[instrument(skip_all, fields(?path.user))]
@@ -115,17 +121,30 @@ pub async fn settings_post(
         return Err(StatusTemplate(StatusCode::NOT_FOUND));
     }
 
-    let mut current = user_repo.load_info().await.unwrap();
-    if current.description != settings.description || current.private != settings.private {
-        current.description = settings.description;
-        current.private = settings.private;
-        user_repo.save_info(&current).await.unwrap();
-    }
+    let default_branch = Some(settings.default_branch.trim())
+        .filter(|branch| !branch.is_empty())
+        .map(ToOwned::to_owned);
+
+    let message = if default_branch.as_deref().is_none_or(validate::branch_name) {
+        let mut current = user_repo.load_info().await.unwrap();
+        if current.description != settings.description
+            || current.private != settings.private
+            || current.theme != settings.theme
+            || current.default_branch != default_branch
+        {
+            current.description = settings.description;
+            current.private = settings.private;
+            current.theme = settings.theme;
+            current.default_branch = default_branch;
+            user_repo.save_info(&current).await.unwrap();
+        }
+
+        templates::user::UserSettingsMessage::Success
+    } else {
+        templates::user::UserSettingsMessage::InvalidBranchName
+    };
 
-    cookies.add(Cookie::new(
-        COOKIE_MESSAGE,
-        templates::user::UserSettingsMessage::Success.as_ref(),
-    ));
+    cookies.add(Cookie::new(COOKIE_MESSAGE, message.as_ref()));
 
     Ok(SetCookies::new(
         redirect::to_user_settings(&path.user),
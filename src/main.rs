@@ -10,6 +10,7 @@ use std::{
 use anyhow::Result;
 use axum::{
     Router,
+    extract::DefaultBodyLimit,
     routing::{get, post},
 };
 use tokio::net::TcpListener;
@@ -19,7 +20,10 @@ use tower_http::{compression::CompressionLayer, trace::TraceLayer};
 use tracing::{Level, info};
 use tracing_subscriber::{filter::Targets, prelude::*};
 
-use crate::{middleware::OnionLocationLayer, repositories::SettingsRepository};
+use crate::{
+    middleware::{ErrorPageLayer, OnionLocationLayer},
+    repositories::SettingsRepository,
+};
 
 mod assets;
 mod cookies;
@@ -30,8 +34,10 @@ mod handlers;
 mod middleware;
 mod models;
 mod redirect;
+mod render;
 mod repositories;
 mod response;
+mod scaffold;
 mod ser;
 mod session;
 mod templates;
@@ -49,63 +55,23 @@ async fn main() -> Result<()> {
 
     init_logging();
 
+    // Not awaited, as it's fine to serve requests while the syntaxes are still loading.
+    tokio::task::spawn_blocking(render::init);
+
     let addr = SocketAddr::from((ADDRESS, 8080));
     let shutdown = Shutdown::new()?;
 
     let listener = TcpListener::bind(addr).await?;
     let server = axum::serve(
         listener,
-        Router::new()
-            .route("/{user}/{repo}/{service}", post(handlers::git::pack))
-            .route("/{user}/{repo}/info/refs", get(handlers::git::info_refs))
-            .route("/{user}/{repo}/tree/", get(handlers::repo::tree))
-            .route("/{user}/{repo}/tree/{*path}", get(handlers::repo::tree))
-            .route(
-                "/{user}/{repo}/delete",
-                get(handlers::repo::delete).post(handlers::repo::delete_post),
-            )
-            .route(
-                "/{user}/{repo}/settings",
-                get(handlers::repo::settings).post(handlers::repo::settings_post),
-            )
-            .route("/{user}/{repo}", get(handlers::repo::index))
-            .route("/{user}/password", post(handlers::user::password_post))
-            .route(
-                "/{user}/settings",
-                get(handlers::user::settings).post(handlers::user::settings_post),
-            )
-            .route("/{user}", get(handlers::user::index))
-            .route(assets::WEBFONTS_ROUTE, get(handlers::assets::webfonts))
-            .route(assets::MAIN_CSS_ROUTE, get(handlers::assets::main_css))
-            .route(
-                assets::FAVICON_SVG_ROUTE,
-                get(handlers::assets::favicon_svg),
-            )
-            .route("/settings/dz", post(handlers::admin::settings_dz_post))
-            .route("/settings/tor", post(handlers::admin::settings_tor_post))
-            .route("/settings", get(handlers::admin::settings))
-            .route("/users", get(handlers::user::list))
-            .route(
-                "/repo/create",
-                get(handlers::repo::create).post(handlers::repo::create_post),
-            )
-            .route(
-                "/register",
-                get(handlers::auth::register).post(handlers::auth::register_post),
-            )
-            .route("/logout", post(handlers::auth::logout))
-            .route(
-                "/login",
-                get(handlers::auth::login).post(handlers::auth::login_post),
-            )
-            .route("/", get(handlers::index))
-            .fallback(handlers::handle_404)
+        routes()
             .layer(
                 ServiceBuilder::new()
                     .layer(TraceLayer::new_for_http())
                     .layer(CompressionLayer::new())
                     .layer(OnionLocationLayer::new())
                     .layer(AndThenLayer::new(middleware::security_headers))
+                    .layer(ErrorPageLayer::new())
                     .into_inner(),
             )
             .with_state(AppState {})
@@ -123,6 +89,122 @@ async fn main() -> Result<()> {
 #[derive(Clone)]
 pub struct AppState {}
 
+fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/{user}/{repo}/{service}", post(handlers::git::pack))
+        .route("/{user}/{repo}/info/refs", get(handlers::git::info_refs))
+        .route("/{user}/{repo}/tree/", get(handlers::repo::tree))
+        .route("/{user}/{repo}/tree/{*path}", get(handlers::repo::tree))
+        .route("/{user}/{repo}/raw/{*path}", get(handlers::repo::raw))
+        .route(
+            "/{user}/{repo}/upload/",
+            post(handlers::edit::upload_post)
+                .layer(DefaultBodyLimit::max(handlers::edit::FILE_UPLOAD_LIMIT)),
+        )
+        .route(
+            "/{user}/{repo}/upload/{*path}",
+            post(handlers::edit::upload_post)
+                .layer(DefaultBodyLimit::max(handlers::edit::FILE_UPLOAD_LIMIT)),
+        )
+        .route(
+            "/{user}/{repo}/edit/{*path}",
+            get(handlers::edit::edit).post(handlers::edit::edit_post),
+        )
+        .merge(ref_routes())
+        .route("/{user}/{repo}/search", get(handlers::repo::search))
+        .route(
+            "/{user}/{repo}/delete",
+            get(handlers::repo::delete).post(handlers::repo::delete_post),
+        )
+        .route(
+            "/{user}/{repo}/settings",
+            get(handlers::repo::settings).post(handlers::repo::settings_post),
+        )
+        .route("/{user}/{repo}", get(handlers::repo::index))
+        .route("/{user}/password", post(handlers::user::password_post))
+        .route(
+            "/{user}/settings",
+            get(handlers::user::settings).post(handlers::user::settings_post),
+        )
+        .route("/{user}", get(handlers::user::index))
+        .route(assets::WEBFONTS_ROUTE, get(handlers::assets::webfonts))
+        .route(assets::DARK_CSS_ROUTE, get(handlers::assets::dark_css))
+        .route(assets::LIGHT_CSS_ROUTE, get(handlers::assets::light_css))
+        .route(
+            assets::FAVICON_SVG_ROUTE,
+            get(handlers::assets::favicon_svg),
+        )
+        .route("/settings/dz", post(handlers::admin::settings_dz_post))
+        .route("/settings/tor", post(handlers::admin::settings_tor_post))
+        .route(
+            "/settings/branch",
+            post(handlers::admin::settings_branch_post),
+        )
+        .route("/settings", get(handlers::admin::settings))
+        .route("/users", get(handlers::user::list))
+        .route("/explore", get(handlers::explore::index))
+        .route(
+            "/repo/create",
+            get(handlers::repo::create).post(handlers::repo::create_post),
+        )
+        .route(
+            "/register",
+            get(handlers::auth::register).post(handlers::auth::register_post),
+        )
+        .route("/logout", post(handlers::auth::logout))
+        .route(
+            "/login",
+            get(handlers::auth::login).post(handlers::auth::login_post),
+        )
+        .route("/", get(handlers::index))
+        .fallback(handlers::handle_404)
+}
+
+/// Routes to manage the releases, tags and branches of a repo.
+fn ref_routes() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/{user}/{repo}/releases/download/{tag}/{name}",
+            get(handlers::release::download),
+        )
+        .route(
+            "/{user}/{repo}/releases/{tag}/notes",
+            post(handlers::release::notes_post),
+        )
+        .route(
+            "/{user}/{repo}/releases/{tag}/assets",
+            post(handlers::release::asset_post)
+                .layer(DefaultBodyLimit::max(handlers::release::ASSET_UPLOAD_LIMIT)),
+        )
+        .route(
+            "/{user}/{repo}/releases/{tag}/assets/{name}/delete",
+            post(handlers::release::asset_delete_post),
+        )
+        .route("/{user}/{repo}/releases", get(handlers::release::index))
+        .route(
+            "/{user}/{repo}/tags/create",
+            post(handlers::tag::create_post),
+        )
+        .route(
+            "/{user}/{repo}/tags/delete",
+            get(handlers::tag::delete).post(handlers::tag::delete_post),
+        )
+        .route("/{user}/{repo}/compare", get(handlers::compare::compare))
+        .route("/{user}/{repo}/merge", post(handlers::compare::merge_post))
+        .route(
+            "/{user}/{repo}/branches/create",
+            post(handlers::branch::create_post),
+        )
+        .route(
+            "/{user}/{repo}/branches/rename",
+            post(handlers::branch::rename_post),
+        )
+        .route(
+            "/{user}/{repo}/branches/delete",
+            get(handlers::branch::delete).post(handlers::branch::delete_post),
+        )
+}
+
 fn init_logging() {
     tracing_subscriber::registry()
         .with(tracing_subscriber::fmt::layer())
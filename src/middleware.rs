@@ -7,6 +7,7 @@ use std::{
 };
 
 use axum::{
+    extract::FromRequestParts,
     http::{
         HeaderValue, Request,
         header::{
@@ -14,13 +15,13 @@ use axum::{
             X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS, X_XSS_PROTECTION,
         },
     },
-    response::Response,
+    response::{IntoResponse, Response},
 };
 use futures_util::future::BoxFuture;
 use tower::{Layer, Service};
 use tracing::error;
 
-use crate::repositories::SettingsRepository;
+use crate::{extract::User, repositories::SettingsRepository, response::ErrorStatus, templates};
 
 pub async fn security_headers(mut res: Response) -> Result<Response, Infallible> {
     let headers = res.headers_mut();
@@ -28,7 +29,8 @@ pub async fn security_headers(mut res: Response) -> Result<Response, Infallible>
     headers.append(
         CONTENT_SECURITY_POLICY,
         HeaderValue::from_static(
-            "default-src 'none'; font-src 'self'; img-src 'self'; style-src 'self';",
+            "default-src 'none'; font-src 'self'; img-src 'self'; media-src 'self'; \
+             style-src 'self';",
         ),
     );
     headers.append(REFERRER_POLICY, "same-origin".parse().unwrap());
@@ -109,3 +111,72 @@ impl<S> Layer<S> for OnionLocationLayer {
         OnionLocation { inner }
     }
 }
+
+/// Renders error pages again for the logged in user, so they use the color theme of the user.
+/// Handlers can't do that themselves, as the user isn't known in all of them, like when the
+/// request is rejected before the handler runs.
+#[derive(Clone)]
+pub struct ErrorPage<S> {
+    inner: S,
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for ErrorPage<S>
+where
+    S: Service<Request<ReqBody>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    ReqBody: Send + 'static,
+{
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+    type Response = S::Response;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            let headers = req.headers().clone();
+            let mut resp = inner.call(req).await?;
+
+            if let Some(&ErrorStatus(code)) = resp.extensions().get::<ErrorStatus>() {
+                let (mut parts, ()) = Request::new(()).into_parts();
+                parts.headers = headers;
+
+                if let Ok(User(user)) = User::from_request_parts(&mut parts, &()).await {
+                    *resp.body_mut() = templates::Error {
+                        auth_user: Some(user),
+                        code,
+                        message: None,
+                    }
+                    .into_response()
+                    .into_body();
+                }
+            }
+
+            Ok(resp)
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct ErrorPageLayer {
+    _priv: (),
+}
+
+impl ErrorPageLayer {
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl<S> Layer<S> for ErrorPageLayer {
+    type Service = ErrorPage<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ErrorPage { inner }
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
 
 #[derive(Serialize, Deserialize)]
 pub struct Settings {
@@ -6,6 +7,10 @@ pub struct Settings {
     pub key: [u8; 64],
     pub tor: Option<Tor>,
     pub tracing: Option<Tracing>,
+    /// Name of the initial branch of new repos, unless users choose their own. Git's default is
+    /// used if it's not set.
+    #[serde(default)]
+    pub default_branch: Option<String>,
 }
 
 impl Default for Settings {
@@ -14,6 +19,7 @@ impl Default for Settings {
             key: [0; 64],
             tor: None,
             tracing: None,
+            default_branch: None,
         }
     }
 }
@@ -34,6 +40,22 @@ pub struct UserAccount {
     pub description: String,
     pub private: bool,
     pub admin: bool,
+    #[serde(default)]
+    pub theme: Theme,
+    /// Name of the initial branch of new repos, overriding the server's default.
+    #[serde(default)]
+    pub default_branch: Option<String>,
+}
+
+/// Color theme of the web interface.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// Follow the color scheme preferred by the browser.
+    #[default]
+    System,
+    Light,
+    Dark,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -42,17 +64,87 @@ pub struct UserRepo {
     #[serde(default)]
     pub description: String,
     pub private: bool,
+    /// Unix timestamp (in seconds) of the last push, if the repo was ever pushed to.
+    #[serde(default)]
+    pub pushed: Option<i64>,
+    /// Whether new repos can be created as a copy of this one.
+    #[serde(default)]
+    pub template: bool,
+}
+
+/// Repository as listed on the explore page.
+pub struct RepoSummary {
+    pub user: String,
+    pub name: String,
+    pub description: String,
+    pub pushed: Option<i64>,
+    pub template: bool,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct ReleaseInfo {
+    #[serde(default)]
+    pub notes: String,
+}
+
+pub struct Release {
+    pub tag: String,
+    pub message: Option<String>,
+    pub commit: String,
+    pub notes: String,
+    pub assets: Vec<ReleaseAsset>,
+}
+
+pub struct ReleaseAsset {
+    pub name: String,
+    pub size: u64,
 }
 
 pub struct RepoFile {
     pub name: String,
     pub kind: FileKind,
+    /// Size in bytes, only available for regular and executable files.
+    pub size: Option<u64>,
+    /// Target path of symlinks or the URL of submodules (if it's defined in `.gitmodules`).
+    pub target: Option<String>,
+    pub last_commit: Option<LastCommit>,
+}
+
+impl RepoFile {
+    pub fn new(name: String, kind: FileKind) -> Self {
+        Self {
+            name,
+            kind,
+            size: None,
+            target: None,
+            last_commit: None,
+        }
+    }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone)]
+pub struct LastCommit {
+    pub id: String,
+    pub summary: String,
+    pub author: String,
+    /// Commit time as seconds since the Unix epoch.
+    pub time: i64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum FileKind {
     Directory,
+    Submodule,
     File,
+    Executable,
+    Symlink,
+}
+
+impl FileKind {
+    /// Whether entries of this kind are listed together with directories, before all files.
+    pub fn is_directory_like(self) -> bool {
+        matches!(self, Self::Directory | Self::Submodule)
+    }
 }
 
 pub struct RepoTree {
@@ -61,7 +153,329 @@ pub struct RepoTree {
 }
 
 pub enum TreeKind {
-    Directory(Vec<RepoFile>),
+    Directory {
+        files: Vec<RepoFile>,
+        /// README file in this directory, if there is any.
+        readme: Option<Readme>,
+    },
     Text(String),
-    Binary(usize),
+    /// Markdown file that was rendered to HTML.
+    Markdown {
+        html: String,
+        toc: Vec<Heading>,
+    },
+    /// Text that is too long to be shown completely, with only the beginning of the content.
+    Truncated {
+        preview: String,
+        size: u64,
+    },
+    Binary(u64),
+    /// Image, audio or video file that can be embedded directly in the page.
+    Media {
+        kind: MediaKind,
+        size: u64,
+    },
+    /// Blob that is too large to be loaded at all.
+    TooLarge(u64),
+}
+
+pub struct Readme {
+    pub format: ReadmeFormat,
+    pub content: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ReadmeFormat {
+    Markdown,
+    ReStructuredText,
+    Org,
+    Text,
+}
+
+impl ReadmeFormat {
+    /// Recognized README file names with their format, in order of priority if a directory
+    /// contains several of them. Names are matched case-insensitively.
+    pub const FILE_NAMES: [(&str, Self); 6] = [
+        ("README.md", Self::Markdown),
+        ("README.markdown", Self::Markdown),
+        ("README.rst", Self::ReStructuredText),
+        ("README.org", Self::Org),
+        ("README.txt", Self::Text),
+        ("README", Self::Markdown),
+    ];
+}
+
+/// Heading of a rendered Markdown document, as an entry in its table of contents.
+pub struct Heading {
+    /// Level from 1 to 6, like the HTML `<h1>` to `<h6>` tags.
+    pub level: u8,
+    pub id: String,
+    pub title: String,
+}
+
+/// Definition in a source file, like a function or struct, as an entry in its outline.
+pub struct Symbol {
+    pub kind: SymbolKind,
+    pub name: String,
+    /// Line of the definition, starting at 1.
+    pub line: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    /// Any kind of type, like classes, structs, enums or traits.
+    Type,
+    /// Modules and namespaces.
+    Module,
+}
+
+/// Branch of a repo, compared against the default branch.
+pub struct BranchInfo {
+    pub name: String,
+    /// Amount of commits on this branch that aren't on the default branch.
+    pub ahead: usize,
+    /// Amount of commits on the default branch that aren't on this branch.
+    pub behind: usize,
+}
+
+impl BranchInfo {
+    /// Whether all commits of the branch are contained in the default branch, so it can be deleted
+    /// without losing anything.
+    pub fn is_merged(&self) -> bool {
+        self.ahead == 0
+    }
+}
+
+/// Changes of a head revision compared to a base branch, as reviewed before merging them.
+pub struct Comparison {
+    /// ID of the commit that the base resolved to.
+    pub base_commit: String,
+    /// ID of the commit that the head resolved to.
+    pub head_commit: String,
+    /// Commits of the head that aren't part of the base, newest first.
+    pub commits: Vec<LastCommit>,
+    /// Changes of the head since the point where it split off from the base.
+    pub files: Vec<FileDiff>,
+    /// Whether the diff lines of some files were left out, because the diff is too large.
+    pub truncated: bool,
+    /// Whether the base is an ancestor of the head, so it can be fast-forwarded.
+    pub fast_forward: bool,
+    /// Paths of the files that conflict when merging the head into the base.
+    pub conflicts: Vec<String>,
+}
+
+impl Comparison {
+    /// Whether the head is already fully merged into the base.
+    pub fn up_to_date(&self) -> bool {
+        self.commits.is_empty()
+    }
+}
+
+/// Changes of a single file in a [`Comparison`].
+pub struct FileDiff {
+    pub path: String,
+    /// Previous path of renamed files.
+    pub old_path: Option<String>,
+    pub status: DiffStatus,
+    pub additions: usize,
+    pub deletions: usize,
+    pub binary: bool,
+    pub hunks: Vec<Vec<DiffLine>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DiffStatus {
+    Added,
+    Deleted,
+    Modified,
+    Renamed,
+}
+
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    /// Line number in the old file, if the line exists there.
+    pub old: Option<u32>,
+    /// Line number in the new file, if the line exists there.
+    pub new: Option<u32>,
+    pub content: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DiffLineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+/// How to merge a branch into another one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MergeStrategy {
+    /// Move the base branch forward to the head, without creating any commit.
+    FastForward,
+    /// Create a merge commit with both branches as parents.
+    MergeCommit,
+    /// Combine all changes into a single commit on top of the base branch.
+    Squash,
+}
+
+/// Outcome of merging a branch from the web interface.
+#[derive(Debug, PartialEq, Eq)]
+pub enum MergeOutcome {
+    Merged,
+    /// The head is already part of the base, so there is nothing to merge.
+    UpToDate,
+    /// A fast-forward merge was requested, but the branches diverged.
+    NotFastForward,
+    Conflicts,
+    /// The base branch doesn't point to the commit that was reviewed anymore.
+    BranchMoved,
+    UnknownBranch,
+}
+
+/// Outcome of creating a branch from the web interface.
+#[derive(Debug, PartialEq, Eq)]
+pub enum BranchCreateOutcome {
+    Created,
+    AlreadyExists,
+    /// The revspec to create the branch at doesn't resolve to a commit.
+    UnknownRev,
+}
+
+/// Outcome of renaming a branch from the web interface.
+#[derive(Debug, PartialEq, Eq)]
+pub enum BranchRenameOutcome {
+    Renamed,
+    /// Another branch already has the new name.
+    AlreadyExists,
+    NotFound,
+}
+
+/// Outcome of deleting a branch from the web interface.
+#[derive(Debug, PartialEq, Eq)]
+pub enum BranchDeleteOutcome {
+    Deleted,
+    NotFound,
+    /// The default branch can't be deleted.
+    IsDefault,
+}
+
+/// Outcome of creating a tag from the web interface.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TagCreateOutcome {
+    Created,
+    AlreadyExists,
+    /// The revspec to create the tag at doesn't resolve to a commit.
+    UnknownRev,
+}
+
+/// Outcome of deleting a tag from the web interface.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TagDeleteOutcome {
+    Deleted,
+    NotFound,
+}
+
+/// Outcome of committing changes to a branch from the web interface.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CommitOutcome {
+    /// The changes were committed, resulting in the commit with this ID.
+    Committed(String),
+    /// The changes didn't modify any file, so no commit was created.
+    Unchanged,
+    /// The branch doesn't point to the commit that the changes were based on anymore.
+    BranchMoved,
+    UnknownBranch,
+    /// The file at this path is too large to be committed.
+    TooLarge(String),
+    /// The file at this path is tracked by Git LFS, which can't be committed to from the web.
+    LfsTracked(String),
+    /// The path is invalid, or an existing directory or file is in the way of writing it.
+    InvalidPath(String),
+}
+
+/// Result of a code search within a single revision of a repository.
+#[derive(Default)]
+pub struct SearchResults {
+    pub files: Vec<SearchFile>,
+    /// Whether the search was stopped early, because it hit the time or result limit.
+    pub truncated: bool,
+}
+
+/// File that contains matches of a code search.
+pub struct SearchFile {
+    pub path: String,
+    /// Groups of consecutive lines, each with one or more matches and the context around them.
+    pub hunks: Vec<Vec<SearchLine>>,
+}
+
+pub struct SearchLine {
+    /// Line number, starting at 1.
+    pub number: usize,
+    pub content: String,
+    /// Whether the line contains the search query, or is only context around a match.
+    pub matched: bool,
+}
+
+/// Syntax definition that is available for highlighting.
+pub struct SyntaxInfo {
+    pub name: String,
+    pub extensions: Vec<String>,
+    /// Whether the syntax was loaded from the data directory, instead of being built in.
+    pub custom: bool,
+}
+
+/// Raw content of a file, which is streamed in chunks while it's being read.
+pub struct RawBlob {
+    /// Size of the whole blob in bytes.
+    pub size: u64,
+    /// Whether the whole blob is valid UTF-8.
+    pub text: bool,
+    /// Inclusive byte range that is streamed, or `None` if it's the whole blob.
+    pub range: Option<(u64, u64)>,
+    pub content: mpsc::Receiver<Vec<u8>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Image,
+    Audio,
+    Video,
+}
+
+impl MediaKind {
+    /// Detect the media kind and its MIME type from the extension of a file name.
+    pub fn detect(name: &str) -> Option<(Self, &'static str)> {
+        let (_, ext) = name.rsplit_once('.')?;
+
+        Some(match ext.to_ascii_lowercase().as_str() {
+            "png" => (Self::Image, "image/png"),
+            "jpg" | "jpeg" => (Self::Image, "image/jpeg"),
+            "gif" => (Self::Image, "image/gif"),
+            "webp" => (Self::Image, "image/webp"),
+            "avif" => (Self::Image, "image/avif"),
+            "bmp" => (Self::Image, "image/bmp"),
+            "ico" => (Self::Image, "image/x-icon"),
+            "svg" => (Self::Image, "image/svg+xml"),
+            "mp3" => (Self::Audio, "audio/mpeg"),
+            "ogg" | "oga" => (Self::Audio, "audio/ogg"),
+            "opus" => (Self::Audio, "audio/opus"),
+            "wav" => (Self::Audio, "audio/wav"),
+            "flac" => (Self::Audio, "audio/flac"),
+            "m4a" => (Self::Audio, "audio/mp4"),
+            "mp4" | "m4v" => (Self::Video, "video/mp4"),
+            "webm" => (Self::Video, "video/webm"),
+            "ogv" => (Self::Video, "video/ogg"),
+            "mov" => (Self::Video, "video/quicktime"),
+            _ => return None,
+        })
+    }
+}
+
+/// Whether the file name denotes a Markdown file, which is rendered instead of showing its source.
+pub fn is_markdown(name: &str) -> bool {
+    name.rsplit_once('.').is_some_and(|(_, ext)| {
+        ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown")
+    })
 }
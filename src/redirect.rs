@@ -49,6 +49,67 @@ pub fn to_repo_settings(user: &str, repo: &str) -> Redirect {
     Redirect::to(&format!("/{user}/{repo}/settings"))
 }
 
+pub fn to_repo_releases(user: &str, repo: &str) -> Redirect {
+    let user = Cow::from(percent_encoding::utf8_percent_encode(
+        user,
+        NON_ALPHANUMERIC,
+    ));
+    let repo = Cow::from(percent_encoding::utf8_percent_encode(
+        repo,
+        NON_ALPHANUMERIC,
+    ));
+
+    Redirect::to(&format!("/{user}/{repo}/releases"))
+}
+
+pub fn to_repo_tree(user: &str, repo: &str, path: &str, branch: &str) -> Redirect {
+    let user = Cow::from(percent_encoding::utf8_percent_encode(
+        user,
+        NON_ALPHANUMERIC,
+    ));
+    let repo = Cow::from(percent_encoding::utf8_percent_encode(
+        repo,
+        NON_ALPHANUMERIC,
+    ));
+    let path = path
+        .split('/')
+        .map(|name| {
+            Cow::from(percent_encoding::utf8_percent_encode(
+                name,
+                NON_ALPHANUMERIC,
+            ))
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+    let branch = Cow::from(percent_encoding::utf8_percent_encode(
+        branch,
+        NON_ALPHANUMERIC,
+    ));
+
+    Redirect::to(&format!("/{user}/{repo}/tree/{path}?branch={branch}"))
+}
+
+pub fn to_repo_compare(user: &str, repo: &str, base: &str, head: &str) -> Redirect {
+    let user = Cow::from(percent_encoding::utf8_percent_encode(
+        user,
+        NON_ALPHANUMERIC,
+    ));
+    let repo = Cow::from(percent_encoding::utf8_percent_encode(
+        repo,
+        NON_ALPHANUMERIC,
+    ));
+    let base = Cow::from(percent_encoding::utf8_percent_encode(
+        base,
+        NON_ALPHANUMERIC,
+    ));
+    let head = Cow::from(percent_encoding::utf8_percent_encode(
+        head,
+        NON_ALPHANUMERIC,
+    ));
+
+    Redirect::to(&format!("/{user}/{repo}/compare?base={base}&head={head}"))
+}
+
 pub fn to_user_index(user: &str) -> Redirect {
     let user = Cow::from(percent_encoding::utf8_percent_encode(
         user,
@@ -103,6 +164,22 @@ mod tests {
             "/hello/world/settings",
             get_location(to_repo_settings("hello", "world"))
         );
+        assert_eq!(
+            "/hello/world/releases",
+            get_location(to_repo_releases("hello", "world"))
+        );
+        assert_eq!(
+            "/hello/world/tree/assets/player%2Epng?branch=main",
+            get_location(to_repo_tree("hello", "world", "assets/player.png", "main"))
+        );
+        assert_eq!(
+            "/hello/world/compare?base=main&head=feature%2Fjump",
+            get_location(to_repo_compare("hello", "world", "main", "feature/jump"))
+        );
+        assert_eq!(
+            "/hello/world/tree/?branch=feature%2Fjump",
+            get_location(to_repo_tree("hello", "world", "", "feature/jump"))
+        );
         assert_eq!("/hello", get_location(to_user_index("hello")));
         assert_eq!("/hello/settings", get_location(to_user_settings("hello")));
     }
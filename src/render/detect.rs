@@ -0,0 +1,217 @@
+//! Detection of the syntax that a file is highlighted with.
+
+use syntect::parsing::SyntaxReference;
+
+use super::SYNTAX_SET;
+
+/// Well known file names that aren't covered by the file extensions of the bundled syntaxes,
+/// mapped to a token that identifies their syntax.
+const FILE_NAMES: &[(&str, &str)] = &[
+    ("APKBUILD", "sh"),
+    ("BUCK", "py"),
+    ("BUILD", "py"),
+    ("BUILD.bazel", "py"),
+    ("Cargo.lock", "toml"),
+    ("Jenkinsfile", "groovy"),
+    ("PKGBUILD", "sh"),
+    ("Pipfile", "toml"),
+    ("WORKSPACE", "py"),
+    (".bash_history", "sh"),
+    (".zlogin", "sh"),
+    (".zlogout", "sh"),
+    (".zprofile", "sh"),
+    (".zshenv", "sh"),
+    (".zshrc", "sh"),
+];
+
+/// Alternative language names, as used by interpreters, editors and Linguist, that don't match the
+/// name or any file extension of a syntax.
+const ALIASES: &[(&str, &str)] = &[
+    ("ash", "sh"),
+    ("console", "sh"),
+    ("dash", "sh"),
+    ("elisp", "el"),
+    ("emacs-lisp", "el"),
+    ("golang", "go"),
+    ("ksh", "sh"),
+    ("node", "js"),
+    ("nodejs", "js"),
+    ("plaintext", "txt"),
+    ("rscript", "r"),
+    ("shell", "sh"),
+    ("shell-script", "sh"),
+    ("shellsession", "sh"),
+    ("starlark", "py"),
+    ("text", "txt"),
+];
+
+/// Find the syntax to highlight a file with. In order of precedence, the syntax is picked by:
+///
+/// 1. The explicit `language`, usually a `linguist-language` attribute.
+/// 2. An editor modeline or the shebang in the first line of the content.
+/// 3. The full file name, like `Makefile` or `Gemfile`.
+/// 4. The file extension.
+/// 5. Patterns that syntaxes define for the first line, like `<?xml`. These are only guesses, so
+///    they're not allowed to override the extension.
+pub fn detect_syntax(
+    name: &str,
+    content: &str,
+    language: Option<&str>,
+) -> &'static SyntaxReference {
+    let first_line = content.lines().next().unwrap_or_default();
+
+    language
+        .and_then(find_syntax_by_language)
+        .or_else(|| modeline(first_line).and_then(find_syntax_by_language))
+        .or_else(|| shebang(first_line).and_then(find_syntax_by_language))
+        .or_else(|| find_syntax_by_file_name(name))
+        .or_else(|| {
+            name.rsplit_once('.')
+                .filter(|(stem, _)| !stem.is_empty())
+                .and_then(|(_, ext)| SYNTAX_SET.find_syntax_by_extension(ext))
+        })
+        .or_else(|| SYNTAX_SET.find_syntax_by_first_line(first_line))
+        .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text())
+}
+
+/// Find a syntax by a language name like `Rust`, `C++` or `shell`, ignoring case. File
+/// extensions work as well, as they are commonly used to name languages.
+pub fn find_syntax_by_language(language: &str) -> Option<&'static SyntaxReference> {
+    let language = language.trim().to_ascii_lowercase();
+    let language = ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == language)
+        .map_or(language.as_str(), |(_, token)| token);
+
+    SYNTAX_SET.find_syntax_by_token(language).or_else(|| {
+        // Linguist replaces spaces in language names with dashes, like `Emacs-Lisp`.
+        let language = language.replace('-', " ");
+        SYNTAX_SET
+            .syntaxes()
+            .iter()
+            .find(|syntax| syntax.name.eq_ignore_ascii_case(&language))
+    })
+}
+
+/// Find a syntax by the full file name. Some syntaxes list file names like `Makefile` among their
+/// extensions, but these must match exactly, so a file named `build` isn't taken for a `.build`
+/// file.
+fn find_syntax_by_file_name(name: &str) -> Option<&'static SyntaxReference> {
+    FILE_NAMES
+        .iter()
+        .find(|(file_name, _)| *file_name == name)
+        .and_then(|(_, token)| SYNTAX_SET.find_syntax_by_token(token))
+        .or_else(|| {
+            SYNTAX_SET
+                .syntaxes()
+                .iter()
+                .find(|syntax| syntax.file_extensions.iter().any(|ext| ext == name))
+        })
+}
+
+/// Extract the language from a Vim (`vim: set ft=rust:`) or Emacs (`-*- mode: rust -*-`)
+/// modeline.
+fn modeline(line: &str) -> Option<&str> {
+    if let Some((_, rest)) = line.split_once("-*-") {
+        let (vars, _) = rest.split_once("-*-")?;
+
+        return if vars.contains(':') {
+            vars.split(';').find_map(|var| {
+                let (key, value) = var.split_once(':')?;
+                key.trim()
+                    .eq_ignore_ascii_case("mode")
+                    .then(|| value.trim())
+            })
+        } else {
+            Some(vars.trim())
+        }
+        .filter(|mode| !mode.is_empty());
+    }
+
+    let (_, options) = ["vim:", "vi:", "ex:"].iter().find_map(|marker| {
+        line.match_indices(marker)
+            .find(|(pos, _)| {
+                line[..*pos]
+                    .chars()
+                    .next_back()
+                    .is_none_or(char::is_whitespace)
+            })
+            .map(|(pos, _)| line.split_at(pos + marker.len()))
+    })?;
+
+    options
+        .split(|c: char| c == ':' || c.is_whitespace())
+        .filter_map(|option| option.split_once('='))
+        .find(|(key, _)| matches!(*key, "ft" | "filetype" | "syn" | "syntax"))
+        .map(|(_, value)| value)
+        .filter(|value| !value.is_empty())
+}
+
+/// Extract the interpreter from a shebang like `#!/usr/bin/env python3`, without any version
+/// suffix.
+fn shebang(line: &str) -> Option<&str> {
+    let mut args = line.strip_prefix("#!")?.split_whitespace();
+    let mut interpreter = args.next()?.rsplit('/').next()?;
+
+    if interpreter == "env" {
+        interpreter = args.find(|arg| !arg.starts_with('-') && !arg.contains('='))?;
+    }
+
+    Some(interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.'))
+        .filter(|interpreter| !interpreter.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modelines() {
+        assert_eq!(Some("rust"), modeline("// vim: set ft=rust ts=4:"));
+        assert_eq!(Some("python"), modeline("# vi:syntax=python"));
+        assert_eq!(
+            Some("c++"),
+            modeline("/* -*- mode: c++; tab-width: 4 -*- */")
+        );
+        assert_eq!(Some("Go"), modeline("// -*- Go -*-"));
+        assert_eq!(None, modeline("let envim: set ft=rust"));
+        assert_eq!(None, modeline("fn main() {}"));
+    }
+
+    #[test]
+    fn shebangs() {
+        assert_eq!(Some("bash"), shebang("#!/bin/bash -e"));
+        assert_eq!(Some("python"), shebang("#!/usr/bin/env python3.12"));
+        assert_eq!(
+            Some("node"),
+            shebang("#!/usr/bin/env -S FOO=1 node --harmony")
+        );
+        assert_eq!(None, shebang("#!/usr/bin/env"));
+        assert_eq!(None, shebang("# comment"));
+    }
+
+    #[test]
+    fn syntax_detection() {
+        let detect = |name, content, language| detect_syntax(name, content, language).name.as_str();
+
+        assert_eq!("Makefile", detect("Makefile", "all:\n", None));
+        assert_eq!(
+            "Ruby",
+            detect("Gemfile", "source 'https://rubygems.org'\n", None)
+        );
+        assert_eq!("Groovy", detect("Jenkinsfile", "pipeline {}\n", None));
+        assert_eq!("Python", detect("build", "#!/usr/bin/env python3\n", None));
+        assert_eq!(
+            "Bourne Again Shell (bash)",
+            detect("run", "#!/bin/sh\necho hi\n", None)
+        );
+        assert_eq!("Rust", detect("main.c", "// vim: ft=rust\n", None));
+        assert_eq!("Rust", detect("main.c", "int main;\n", Some("Rust")));
+        assert_eq!("Lisp", detect("init", "", Some("Emacs-Lisp")));
+        assert_eq!("YAML", detect("config.yml", "--- !ruby/object\n", None));
+        assert_eq!("XML", detect("feed", "<?xml version=\"1.0\"?>\n", None));
+        assert_eq!("Plain Text", detect("LICENSE", "MIT License\n", None));
+        assert_eq!("Plain Text", detect("Build", "all\n", None));
+        assert_eq!("Plain Text", detect(".rs", "fn main() {}\n", None));
+    }
+}
@@ -0,0 +1,491 @@
+//! Basic conversion of other markup languages to Markdown, so they can be rendered with the same
+//! pipeline. Only the common subset that is typically found in README files is supported, anything
+//! else ends up as plain text.
+
+use std::fmt::Write;
+
+/// Convert an [Org](https://orgmode.org) document to Markdown.
+pub fn org_to_markdown(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut block = None;
+    let mut drawer = false;
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        let keyword = trimmed.to_ascii_uppercase();
+
+        match block {
+            Some(OrgBlock::Code) if keyword.starts_with("#+END_") => {
+                output.push_str("```\n");
+                block = None;
+            }
+            Some(OrgBlock::Code) => {
+                output.push_str(line);
+                output.push('\n');
+            }
+            Some(OrgBlock::Quote) if keyword.starts_with("#+END_") => {
+                output.push('\n');
+                block = None;
+            }
+            Some(OrgBlock::Quote) => {
+                writeln!(output, "> {}", org_inline(trimmed)).unwrap();
+            }
+            None if drawer => drawer = keyword != ":END:",
+            None if keyword == ":PROPERTIES:" || keyword == ":LOGBOOK:" => drawer = true,
+            None if keyword.starts_with("#+BEGIN_SRC") => {
+                let lang = trimmed["#+BEGIN_SRC".len()..].split_whitespace().next();
+                writeln!(output, "```{}", lang.unwrap_or_default()).unwrap();
+                block = Some(OrgBlock::Code);
+            }
+            None if keyword.starts_with("#+BEGIN_EXAMPLE") => {
+                output.push_str("```\n");
+                block = Some(OrgBlock::Code);
+            }
+            None if keyword.starts_with("#+BEGIN_QUOTE") => {
+                block = Some(OrgBlock::Quote);
+            }
+            None if keyword.starts_with("#+TITLE:") => {
+                writeln!(
+                    output,
+                    "# {}\n",
+                    org_inline(trimmed["#+TITLE:".len()..].trim())
+                )
+                .unwrap();
+            }
+            // Any other keywords, as well as comments.
+            None if trimmed.starts_with("#+") || trimmed == "#" || trimmed.starts_with("# ") => {}
+            None if trimmed.len() >= 5 && trimmed.chars().all(|c| c == '-') => {
+                output.push_str("\n***\n\n");
+            }
+            None => {
+                let stars = line.chars().take_while(|&c| c == '*').count();
+
+                if stars > 0 && line[stars..].starts_with(' ') {
+                    writeln!(
+                        output,
+                        "{} {}",
+                        "#".repeat(stars.min(6)),
+                        org_inline(line[stars..].trim())
+                    )
+                    .unwrap();
+                } else if let Some(item) = ["- ", "+ ", "* "]
+                    .iter()
+                    .find_map(|bullet| trimmed.strip_prefix(bullet))
+                {
+                    let indent = line.len() - line.trim_start().len();
+                    writeln!(output, "{}- {}", " ".repeat(indent), org_inline(item)).unwrap();
+                } else {
+                    output.push_str(&org_inline(trimmed));
+                    output.push('\n');
+                }
+            }
+        }
+    }
+
+    if block == Some(OrgBlock::Code) {
+        output.push_str("```\n");
+    }
+
+    output
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OrgBlock {
+    Code,
+    Quote,
+}
+
+/// Convert inline Org markup like `*bold*`, `/italic/`, `=code=` and `[[link][description]]`.
+fn org_inline(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut prev = None;
+
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("[[")
+            && let Some(end) = after.find("]]")
+        {
+            let link = &after[..end];
+            let (target, desc) = link.split_once("][").unwrap_or((link, link));
+            write!(output, "[{}]({target})", org_inline(desc)).unwrap();
+
+            rest = &after[end + 2..];
+            prev = Some(']');
+            continue;
+        }
+
+        let markdown = match c {
+            '*' => Some("**"),
+            '/' => Some("*"),
+            '=' | '~' => Some("`"),
+            '+' => Some("~~"),
+            _ => None,
+        };
+
+        if let Some(markdown) = markdown
+            && prev.is_none_or(|prev: char| prev.is_whitespace() || "-({'\"".contains(prev))
+            && let Some(end) = find_closing(&rest[1..], c)
+        {
+            let content = &rest[1..=end];
+            output.push_str(markdown);
+            if markdown == "`" {
+                output.push_str(content);
+            } else {
+                output.push_str(&org_inline(content));
+            }
+            output.push_str(markdown);
+
+            rest = &rest[end + 2..];
+            prev = Some(c);
+            continue;
+        }
+
+        output.push(c);
+        rest = &rest[c.len_utf8()..];
+        prev = Some(c);
+    }
+
+    output
+}
+
+/// Find the position of the closing emphasis marker, which must directly follow non-whitespace
+/// content and be followed by whitespace, punctuation or the end of the text.
+fn find_closing(text: &str, marker: char) -> Option<usize> {
+    if text.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    text.match_indices(marker).map(|(pos, _)| pos).find(|&pos| {
+        pos > 0
+            && !text[..pos].ends_with(char::is_whitespace)
+            && text[pos + 1..]
+                .chars()
+                .next()
+                .is_none_or(|next| next.is_whitespace() || "-.,;:!?')}\"".contains(next))
+    })
+}
+
+/// Convert a [reStructuredText](https://docutils.sourceforge.io/rst.html) document to Markdown.
+pub fn rst_to_markdown(text: &str) -> String {
+    let lines = text.lines().collect::<Vec<_>>();
+    let mut output = String::with_capacity(text.len());
+    // Adornment styles of section titles, in the order they appear, which defines their levels.
+    let mut styles = Vec::new();
+    let mut literal = false;
+    let mut i = 0;
+
+    let mut heading = |output: &mut String, title: &str, style: (char, bool)| {
+        let level = if let Some(pos) = styles.iter().position(|&s| s == style) {
+            pos + 1
+        } else {
+            styles.push(style);
+            styles.len()
+        };
+        writeln!(
+            output,
+            "{} {}\n",
+            "#".repeat(level.min(6)),
+            rst_inline(title)
+        )
+        .unwrap();
+    };
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim();
+
+        // Section title with overline and underline.
+        if let Some(c) = adornment(line)
+            && i + 2 < lines.len()
+            && !lines[i + 1].trim().is_empty()
+            && adornment(lines[i + 2]) == Some(c)
+        {
+            heading(&mut output, lines[i + 1].trim(), (c, true));
+            i += 3;
+            continue;
+        }
+
+        // Section title with underline only.
+        if !trimmed.is_empty()
+            && !line.starts_with(char::is_whitespace)
+            && let Some(c) = lines.get(i + 1).and_then(|next| adornment(next))
+            && lines[i + 1].trim_end().chars().count() >= trimmed.chars().count()
+        {
+            heading(&mut output, trimmed, (c, false));
+            i += 2;
+            continue;
+        }
+
+        if adornment(line).is_some() && trimmed.len() >= 4 {
+            output.push_str("\n***\n\n");
+            i += 1;
+            continue;
+        }
+
+        if literal && line.starts_with(char::is_whitespace) && !trimmed.is_empty() {
+            let end = indented_block(&lines, i);
+            push_code_block(&mut output, "", &lines[i..end]);
+            literal = false;
+            i = end;
+            continue;
+        }
+
+        // Explicit markup needs whitespace after the dots, otherwise it's an ellipsis in prose.
+        let explicit = trimmed
+            .strip_prefix(".. ")
+            .or_else(|| (trimmed == "..").then_some(""));
+
+        if let Some(directive) = explicit {
+            let end = indented_block(&lines, i + 1);
+            let content = &lines[i + 1..end];
+
+            if let Some((name, arg)) = directive.split_once("::") {
+                rst_directive(&mut output, name.trim(), arg.trim(), content);
+            }
+
+            i = end;
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            output.push('\n');
+        } else if let Some(text) = trimmed.strip_suffix("::") {
+            let text = text.trim_end();
+            if !text.is_empty() {
+                writeln!(output, "{}:", rst_inline(text)).unwrap();
+            }
+            literal = true;
+        } else if let Some(item) = ["- ", "* ", "+ ", "#. "]
+            .iter()
+            .find_map(|bullet| trimmed.strip_prefix(bullet))
+        {
+            let indent = line.len() - line.trim_start().len();
+            let bullet = if trimmed.starts_with('#') { "1." } else { "-" };
+            writeln!(
+                output,
+                "{}{bullet} {}",
+                " ".repeat(indent),
+                rst_inline(item)
+            )
+            .unwrap();
+        } else {
+            output.push_str(&rst_inline(trimmed));
+            output.push('\n');
+        }
+
+        i += 1;
+    }
+
+    output
+}
+
+/// Check whether the line is a section adornment, a repetition of a single punctuation character,
+/// and return that character.
+fn adornment(line: &str) -> Option<char> {
+    let line = line.trim_end();
+    let c = line.chars().next()?;
+
+    (line.len() >= 2 && c.is_ascii_punctuation() && line.chars().all(|other| other == c))
+        .then_some(c)
+}
+
+/// Find the end of the indented block starting at the given line, excluding trailing empty lines.
+fn indented_block(lines: &[&str], start: usize) -> usize {
+    let mut end = start;
+
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if !line.starts_with(char::is_whitespace) {
+            break;
+        }
+        end = i + 1;
+    }
+
+    end
+}
+
+/// Remove the common indentation of the given lines.
+fn dedent<'a>(lines: &[&'a str]) -> Vec<&'a str> {
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or_default();
+
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or_default())
+        .collect()
+}
+
+fn push_code_block(output: &mut String, lang: &str, lines: &[&str]) {
+    writeln!(output, "```{lang}").unwrap();
+    for line in dedent(lines) {
+        output.push_str(line);
+        output.push('\n');
+    }
+    output.push_str("```\n");
+}
+
+fn rst_directive(output: &mut String, name: &str, arg: &str, content: &[&str]) {
+    let content = dedent(content);
+    // Directive options like `:alt: text` directly follow the directive.
+    let options = content
+        .iter()
+        .take_while(|line| line.starts_with(':'))
+        .filter_map(|line| {
+            let (key, value) = line[1..].split_once(':')?;
+            Some((key, value.trim()))
+        })
+        .collect::<Vec<_>>();
+    let body = &content[options.len()..];
+    let body = &body[body.iter().take_while(|line| line.is_empty()).count()..];
+
+    match name {
+        "code" | "code-block" | "sourcecode" => push_code_block(output, arg, body),
+        "image" | "figure" => {
+            let alt = options
+                .iter()
+                .find_map(|&(key, value)| (key == "alt").then_some(value))
+                .unwrap_or_default();
+            writeln!(output, "![{alt}]({arg})").unwrap();
+        }
+        "note" | "tip" | "hint" | "important" | "attention" | "caution" | "warning" | "danger"
+        | "error" => {
+            let mut title = name.to_owned();
+            title[..1].make_ascii_uppercase();
+
+            writeln!(output, "> **{title}:** {}", rst_inline(arg)).unwrap();
+            if !body.is_empty() {
+                output.push_str(">\n");
+            }
+            for line in body {
+                writeln!(output, "> {}", rst_inline(line.trim())).unwrap();
+            }
+            output.push('\n');
+        }
+        _ => {}
+    }
+}
+
+/// Convert inline reStructuredText markup like ``` ``code`` ```, `` `link <url>`_ `` and roles.
+/// Emphasis with `*` and `**` is the same as in Markdown and left as is.
+fn rst_inline(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut code = false;
+
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("``")
+            && let Some(end) = after.find("``")
+        {
+            write!(output, "`{}`", &after[..end]).unwrap();
+            rest = &after[end + 2..];
+            continue;
+        }
+
+        // Roles like :code:`text` are shown as code, regardless of the role.
+        if let Some(after) = rest.strip_prefix(':')
+            && let Some(end) = after.find(':')
+            && end > 0
+            && after[..end]
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
+            && after[end + 1..].starts_with('`')
+        {
+            rest = &after[end + 1..];
+            code = true;
+            continue;
+        }
+
+        if let Some(after) = rest.strip_prefix('`')
+            && let Some(end) = after.find('`')
+        {
+            let content = &after[..end];
+            let tail = &after[end + 1..];
+
+            if let Some(tail) = tail.strip_prefix("__").or_else(|| tail.strip_prefix('_')) {
+                match content
+                    .strip_suffix('>')
+                    .and_then(|content| content.rsplit_once('<'))
+                {
+                    Some((text, url)) => write!(output, "[{}]({url})", text.trim()).unwrap(),
+                    None => output.push_str(content),
+                }
+                rest = tail;
+            } else {
+                if code {
+                    write!(output, "`{content}`").unwrap();
+                } else {
+                    write!(output, "*{content}*").unwrap();
+                }
+                rest = tail;
+            }
+
+            code = false;
+            continue;
+        }
+
+        output.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn org() {
+        let org = "#+TITLE: My Game\n#+AUTHOR: Alice\n\n* Install\n\nRun =make= or see \
+                   [[https://example.com][the *docs*]].\n\n** Controls\n- /Arrows/ move\n  + \
+                   *Space* jumps\n\n#+BEGIN_SRC sh\n  make run\n#+END_SRC\n# comment\n";
+
+        assert_eq!(
+            "# My Game\n\n\n# Install\n\nRun `make` or see [the **docs**](https://example.com).\n\
+             \n## Controls\n- *Arrows* move\n  - **Space** jumps\n\n```sh\n  make run\n```\n",
+            org_to_markdown(org)
+        );
+    }
+
+    #[test]
+    fn org_inline_markers() {
+        assert_eq!("a/b and 2*3*4", org_inline("a/b and 2*3*4"));
+        assert_eq!("*it*, **bold**.", org_inline("/it/, *bold*."));
+        assert_eq!("~~gone~~ `x = 1`", org_inline("+gone+ ~x = 1~"));
+        assert_eq!("* not bold *", org_inline("* not bold *"));
+    }
+
+    #[test]
+    fn rst() {
+        let rst = "=======\nMy Game\n=======\n\nInstall\n-------\n\nRun ``make`` or see \
+                   `the docs <https://example.com>`_.\n\n- *Arrows* move\n\nExample::\n\n    \
+                   make run\n\n.. code-block:: rust\n\n   fn main() {}\n\n.. image:: shot.png\n   \
+                   :alt: Screenshot\n\n.. note:: Beta\n\n   Use :kbd:`F1`.\n\n.. a comment\n\n\
+                   Usage\n-----\n";
+
+        assert_eq!(
+            "# My Game\n\n\n## Install\n\n\nRun `make` or see [the docs](https://example.com).\n\n\
+             - *Arrows* move\n\nExample:\n\n```\nmake run\n```\n\n```rust\nfn main() {}\n```\n\n\
+             ![Screenshot](shot.png)\n\n> **Note:** Beta\n>\n> Use `F1`.\n\n\n\n## Usage\n\n",
+            rst_to_markdown(rst)
+        );
+    }
+
+    #[test]
+    fn rst_ellipsis() {
+        assert_eq!(
+            "Wait for it\n...and then jump.\n",
+            rst_to_markdown("Wait for it\n...and then jump.\n")
+        );
+        assert_eq!(
+            "Before\n\n\nAfter\n",
+            rst_to_markdown("Before\n\n..\n\nAfter\n")
+        );
+    }
+}
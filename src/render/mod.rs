@@ -0,0 +1,680 @@
+use std::{borrow::Cow, collections::HashSet, fs, io, sync::LazyLock};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use percent_encoding::NON_ALPHANUMERIC;
+use pulldown_cmark::{CodeBlockKind, Event, Tag, TagEnd};
+use syntect::{
+    html::{self as syntect_html, ClassStyle, ClassedHTMLGenerator},
+    parsing::{ParseState, ScopeStack, SyntaxDefinition, SyntaxReference, SyntaxSet},
+    util::LinesWithEndings,
+};
+use tracing::{info, instrument, warn};
+
+use crate::{
+    dirs::DIRS,
+    models::{Heading, Readme, ReadmeFormat, Symbol, SyntaxInfo},
+};
+
+mod detect;
+mod markup;
+mod outline;
+
+pub use detect::{detect_syntax, find_syntax_by_language};
+
+pub static SYNTAX_SET: LazyLock<SyntaxSet> = LazyLock::new(|| {
+    let mut builder = SyntaxSet::load_defaults_newlines().into_builder();
+    builder.add(
+        SyntaxDefinition::load_from_str(
+            include_str!("../../assets/TOML.sublime-syntax"),
+            true,
+            Some("TOML"),
+        )
+        .unwrap(),
+    );
+    // Added last, so they take precedence over the built-in syntaxes for the same extensions.
+    for syntax in CUSTOM_SYNTAXES.iter() {
+        builder.add(syntax.clone());
+    }
+    builder.build()
+});
+
+/// Additional syntax definitions, loaded from the `syntaxes` folder of the data directory.
+static CUSTOM_SYNTAXES: LazyLock<Vec<SyntaxDefinition>> =
+    LazyLock::new(|| load_syntaxes(&DIRS.syntaxes_dir()));
+
+/// Load all syntax definitions, so the first highlighted file doesn't have to wait for it and
+/// broken custom definitions are reported right away.
+pub fn init() {
+    LazyLock::force(&SYNTAX_SET);
+}
+
+/// Load all `.sublime-syntax` files from the directory. Definitions that fail to load are
+/// skipped, so a single broken file doesn't prevent the server from starting.
+fn load_syntaxes(dir: &Utf8Path) -> Vec<SyntaxDefinition> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Vec::new(),
+        Err(error) => {
+            warn!(%error, %dir, "failed reading custom syntaxes");
+            return Vec::new();
+        }
+    };
+
+    let mut paths = entries
+        .filter_map(|entry| Utf8PathBuf::from_path_buf(entry.ok()?.path()).ok())
+        .filter(|path| path.extension() == Some("sublime-syntax"))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let syntax = fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|content| {
+                    SyntaxDefinition::load_from_str(&content, true, path.file_stem())
+                        .map_err(Into::into)
+                });
+
+            match syntax {
+                Ok(syntax) => {
+                    info!(%path, name = syntax.name, "loaded custom syntax");
+                    Some(syntax)
+                }
+                Err(error) => {
+                    warn!(%error, %path, "failed loading custom syntax");
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// List all syntaxes that can be used for highlighting, except for the hidden ones that are only
+/// included by other syntaxes. Custom syntaxes are recognized by their scope.
+pub fn syntaxes() -> Vec<SyntaxInfo> {
+    let custom = CUSTOM_SYNTAXES
+        .iter()
+        .map(|syntax| syntax.scope)
+        .collect::<HashSet<_>>();
+
+    SYNTAX_SET
+        .syntaxes()
+        .iter()
+        .filter(|syntax| !syntax.hidden)
+        .map(|syntax| SyntaxInfo {
+            name: syntax.name.clone(),
+            extensions: syntax.file_extensions.clone(),
+            custom: custom.contains(&syntax.scope),
+        })
+        .collect()
+}
+
+/// Location of a Markdown document inside a repository, used to resolve relative links and images
+/// against the tree and raw routes.
+#[derive(Clone)]
+pub struct LinkBase {
+    pub user: String,
+    pub repo: String,
+    /// Revspec that the document was loaded from.
+    pub rev: String,
+    /// Directory that contains the document, relative to the repository root.
+    pub dir: Utf8PathBuf,
+}
+
+impl LinkBase {
+    /// Resolve a link destination to a route of this repository. Returns [`None`] for anything
+    /// that isn't a relative path, like full URLs, absolute paths or plain fragments.
+    fn resolve(&self, dest: &str, raw: bool) -> Option<String> {
+        if dest.is_empty() || dest.starts_with(['/', '#', '?']) || has_scheme(dest) {
+            return None;
+        }
+
+        let (path, fragment) = match dest.find(['?', '#']) {
+            Some(pos) => {
+                let suffix = &dest[pos..];
+                (
+                    &dest[..pos],
+                    suffix.find('#').map_or("", |pos| &suffix[pos..]),
+                )
+            }
+            None => (dest, ""),
+        };
+
+        let mut segments = self
+            .dir
+            .as_str()
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect::<Vec<_>>();
+
+        for segment in path.split('/') {
+            match segment {
+                "" | "." => {}
+                ".." => {
+                    segments.pop();
+                }
+                segment => segments.push(segment),
+            }
+        }
+
+        let user = Cow::from(percent_encoding::utf8_percent_encode(
+            &self.user,
+            NON_ALPHANUMERIC,
+        ));
+        let repo = Cow::from(percent_encoding::utf8_percent_encode(
+            &self.repo,
+            NON_ALPHANUMERIC,
+        ));
+        let rev = Cow::from(percent_encoding::utf8_percent_encode(
+            &self.rev,
+            NON_ALPHANUMERIC,
+        ));
+
+        Some(format!(
+            "/{user}/{repo}/{}/{}?branch={rev}{fragment}",
+            if raw { "raw" } else { "tree" },
+            segments.join("/"),
+        ))
+    }
+}
+
+/// Whether the link starts with a URL scheme like `https:` or `mailto:`.
+fn has_scheme(dest: &str) -> bool {
+    dest.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    })
+}
+
+/// Markdown document that was rendered to HTML.
+pub struct Document {
+    pub html: String,
+    /// All headings of the document in order, to build a table of contents from.
+    pub toc: Vec<Heading>,
+}
+
+/// Render a README file according to its format.
+pub fn readme(readme: &Readme, base: &LinkBase) -> Document {
+    match readme.format {
+        ReadmeFormat::Markdown => markdown(&readme.content, base),
+        ReadmeFormat::ReStructuredText => markdown(&markup::rst_to_markdown(&readme.content), base),
+        ReadmeFormat::Org => markdown(&markup::org_to_markdown(&readme.content), base),
+        ReadmeFormat::Text => {
+            let mut html = String::from("<pre>");
+            pulldown_cmark_escape::escape_html(&mut html, &readme.content).unwrap();
+            html.push_str("</pre>");
+
+            Document {
+                html,
+                toc: Vec::new(),
+            }
+        }
+    }
+}
+
+#[allow(clippy::option_if_let_else)]
+#[instrument(skip_all)]
+pub fn markdown(text: &str, base: &LinkBase) -> Document {
+    let mut syntax = None;
+
+    let parser =
+        pulldown_cmark::Parser::new_ext(text, pulldown_cmark::Options::all()).map(|event| {
+            match event {
+                Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang))) => {
+                    syntax = Some(
+                        find_syntax_by_language(&lang)
+                            .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text()),
+                    );
+                    Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang)))
+                }
+                Event::Text(text) => {
+                    // Custom syntaxes may be broken, so fall back to plain text on errors.
+                    match syntax.map(|syntax| highlight(&text, syntax)) {
+                        Some(Ok(html)) => Event::Html(html.into()),
+                        _ => Event::Text(text),
+                    }
+                }
+                Event::End(TagEnd::CodeBlock) => {
+                    syntax = None;
+                    Event::End(TagEnd::CodeBlock)
+                }
+                event => event,
+            }
+        });
+
+    let (events, toc) = anchor_headings(parser);
+
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, events.into_iter());
+
+    let headings = toc.iter().map(|heading| heading.id.clone()).collect();
+
+    Document {
+        html: sanitize(&html, base, headings),
+        toc,
+    }
+}
+
+/// Give every heading a unique ID, so it can be linked to, and append a permalink to it. The ID is
+/// generated from the title, unless the heading has an explicit one. Either way, it gets a numeric
+/// suffix if an earlier heading already uses it.
+fn anchor_headings<'a>(
+    mut events: impl Iterator<Item = Event<'a>>,
+) -> (Vec<Event<'a>>, Vec<Heading>) {
+    let mut output = Vec::new();
+    let mut toc = Vec::new();
+    let mut used = HashSet::new();
+
+    while let Some(event) = events.next() {
+        let Event::Start(Tag::Heading {
+            level,
+            id,
+            classes,
+            attrs,
+        }) = event
+        else {
+            output.push(event);
+            continue;
+        };
+
+        // Consumes the heading's end event as well, which is added back below.
+        let inner = events
+            .by_ref()
+            .take_while(|event| !matches!(event, Event::End(TagEnd::Heading(_))))
+            .collect::<Vec<_>>();
+        let title = inner
+            .iter()
+            .filter_map(|event| match event {
+                Event::Text(text) | Event::Code(text) => Some(text.as_ref()),
+                _ => None,
+            })
+            .collect::<String>();
+
+        let base = id.map_or_else(|| slugify(&title), |id| id.to_string());
+        let mut slug = base.clone();
+        let mut count = 0;
+
+        while !used.insert(slug.clone()) {
+            count += 1;
+            slug = format!("{base}-{count}");
+        }
+
+        let mut anchor = String::from("<a class=\"heading-anchor\" href=\"#");
+        pulldown_cmark_escape::escape_href(&mut anchor, &slug).unwrap();
+        anchor.push_str("\">#</a>");
+
+        output.push(Event::Start(Tag::Heading {
+            level,
+            id: Some(slug.clone().into()),
+            classes,
+            attrs,
+        }));
+        output.extend(inner);
+        output.push(Event::InlineHtml(anchor.into()));
+        output.push(Event::End(TagEnd::Heading(level)));
+
+        toc.push(Heading {
+            level: level as u8,
+            id: slug,
+            title,
+        });
+    }
+
+    (output, toc)
+}
+
+/// Turn a heading title into an ID, similar to GitHub. Letters are lowercased, spaces become
+/// dashes and any other punctuation is removed.
+fn slugify(title: &str) -> String {
+    let slug = title
+        .trim()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            '-' | '_' => Some(c),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .flat_map(char::to_lowercase)
+        .collect::<String>();
+
+    if slug.is_empty() {
+        "section".to_owned()
+    } else {
+        slug
+    }
+}
+
+/// Prefix for IDs from inline HTML in rendered documents, so they can't clash with the IDs of the
+/// page itself. Heading IDs are left as they are, so deep links like `#controls` keep working.
+const ID_PREFIX: &str = "user-content-";
+
+/// Clean up the rendered HTML of a Markdown document, as it may contain arbitrary inline and block
+/// HTML. Only an allowlist of tags and attributes is kept, which excludes any kind of scripts,
+/// event handlers and embeds like `<iframe>`. Relative links and images are resolved against the
+/// given base, and all IDs except for the given heading IDs get the [`ID_PREFIX`].
+fn sanitize(html: &str, base: &LinkBase, headings: HashSet<String>) -> String {
+    let base = base.clone();
+
+    ammonia::Builder::default()
+        // Task list items are rendered as disabled checkboxes.
+        .add_tags(["input"])
+        .add_tag_attributes("input", ["type", "checked", "disabled"])
+        .add_tag_attributes("details", ["open"])
+        .add_generic_attributes(["align", "class", "id"])
+        .id_prefix(Some(ID_PREFIX))
+        .attribute_filter(
+            move |element, attribute, value| match (element, attribute) {
+                // Headings were prefixed along with all other IDs, so undo that for them.
+                ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", "id") => {
+                    match value.strip_prefix(ID_PREFIX) {
+                        Some(id) if headings.contains(id) => Some(id.to_owned().into()),
+                        _ => Some(value.into()),
+                    }
+                }
+                // Links within the document must point to the prefixed IDs, unless they're
+                // headings.
+                ("a", "href") => match value.strip_prefix('#') {
+                    Some(id)
+                        if !id.starts_with(ID_PREFIX)
+                            && !headings.contains(
+                                percent_encoding::percent_decode_str(id)
+                                    .decode_utf8_lossy()
+                                    .as_ref(),
+                            ) =>
+                    {
+                        Some(format!("#{ID_PREFIX}{id}").into())
+                    }
+                    Some(_) => Some(value.into()),
+                    None => Some(base.resolve(value, false).map_or(value.into(), Into::into)),
+                },
+                ("img", "src") => Some(base.resolve(value, true).map_or(value.into(), Into::into)),
+                ("input", "type") => (value == "checkbox").then_some(value.into()),
+                (_, "class") => {
+                    let classes = value
+                        .split_ascii_whitespace()
+                        .filter(|class| {
+                            class.starts_with("highlight-")
+                                || class.starts_with("language-")
+                                || class.starts_with("footnote-")
+                                || class.starts_with("heading-")
+                        })
+                        .collect::<Vec<_>>();
+
+                    (!classes.is_empty()).then(|| classes.join(" ").into())
+                }
+                _ => Some(value.into()),
+            },
+        )
+        .clean(html)
+        .to_string()
+}
+
+#[instrument(skip_all)]
+pub fn highlight(text: &str, syntax: &SyntaxReference) -> Result<String, syntect::Error> {
+    let mut generator = ClassedHTMLGenerator::new_with_class_style(
+        syntax,
+        &SYNTAX_SET,
+        ClassStyle::SpacedPrefixed {
+            prefix: "highlight-",
+        },
+    );
+
+    for line in LinesWithEndings::from(text) {
+        generator.parse_html_for_line_which_includes_newline(line)?;
+    }
+
+    Ok(generator.finalize())
+}
+
+/// Source code that was highlighted line by line.
+pub struct Code {
+    pub lines: Vec<String>,
+    /// Definitions like functions and types found in the code, to navigate it with.
+    pub outline: Vec<Symbol>,
+}
+
+/// Highlight the text like [`highlight`], but split into separate lines. Each line is a complete
+/// HTML fragment without the line break, so spans that continue on the next line are closed at the
+/// end of a line and opened again at the start of the next one.
+#[instrument(skip_all)]
+pub fn highlight_lines(text: &str, syntax: &SyntaxReference) -> Result<Code, syntect::Error> {
+    let mut state = ParseState::new(syntax);
+    let mut stack = ScopeStack::new();
+    let mut lines = Vec::new();
+    let mut outline = outline::Outline::default();
+
+    for (number, line) in (1..).zip(LinesWithEndings::from(text)) {
+        let mut html = String::new();
+
+        for scope in stack.as_slice() {
+            html.push_str("<span class=\"");
+            for (i, atom) in scope.build_string().split('.').enumerate() {
+                if i > 0 {
+                    html.push(' ');
+                }
+                html.push_str("highlight-");
+                html.push_str(atom);
+            }
+            html.push_str("\">");
+        }
+
+        let ops = state.parse_line(line, &SYNTAX_SET)?;
+        outline.add_line(number, line, &ops, &stack)?;
+        let (spans, _) = syntect_html::line_tokens_to_classed_spans(
+            line,
+            &ops,
+            ClassStyle::SpacedPrefixed {
+                prefix: "highlight-",
+            },
+            &mut stack,
+        )?;
+        html.push_str(&spans);
+        html.retain(|c| c != '\n' && c != '\r');
+
+        for _ in stack.as_slice() {
+            html.push_str("</span>");
+        }
+
+        lines.push(html);
+    }
+
+    Ok(Code {
+        lines,
+        outline: outline.finish_outline(),
+    })
+}
+
+/// Split the text into lines of escaped HTML, for text that isn't highlighted.
+pub fn escape_lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| {
+            let mut html = String::with_capacity(line.len());
+            pulldown_cmark_escape::escape_html(&mut html, line).unwrap();
+            html
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SymbolKind;
+
+    fn base(dir: &str) -> LinkBase {
+        LinkBase {
+            user: "alice".to_owned(),
+            repo: "game".to_owned(),
+            rev: "v1.0".to_owned(),
+            dir: dir.into(),
+        }
+    }
+
+    #[test]
+    fn highlighted_lines() {
+        let syntax = SYNTAX_SET.find_syntax_by_extension("rs").unwrap();
+        let lines = highlight_lines("/* a\n<b> */\nfn", syntax).unwrap().lines;
+
+        assert_eq!(3, lines.len());
+        for line in &lines {
+            assert_eq!(
+                line.matches("<span").count(),
+                line.matches("</span>").count()
+            );
+            assert!(!line.contains('\n'));
+        }
+        assert!(lines[1].contains("highlight-comment"));
+        assert!(lines[1].contains("&lt;b&gt;"));
+    }
+
+    #[test]
+    fn code_outline() {
+        let syntax = SYNTAX_SET.find_syntax_by_extension("rs").unwrap();
+        let code = "mod game {\n    pub struct Player;\n\n    impl Player {\n        fn jump(&self) {}\n    }\n}\n\nfn main() {\n    game::Player.jump();\n}\n";
+        let outline = highlight_lines(code, syntax)
+            .unwrap()
+            .outline
+            .into_iter()
+            .map(|symbol| (symbol.kind, symbol.name, symbol.line))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                (SymbolKind::Module, "game".to_owned(), 1),
+                (SymbolKind::Type, "Player".to_owned(), 2),
+                (SymbolKind::Type, "Player".to_owned(), 4),
+                (SymbolKind::Function, "jump".to_owned(), 5),
+                (SymbolKind::Function, "main".to_owned(), 9),
+            ],
+            outline
+        );
+
+        let syntax = SYNTAX_SET.find_syntax_by_extension("py").unwrap();
+        let outline = highlight_lines("class Game:\n    def run(self):\n        pass\n", syntax)
+            .unwrap()
+            .outline;
+        assert_eq!(
+            vec!["Game", "run"],
+            outline
+                .iter()
+                .map(|symbol| symbol.name.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn relative_links() {
+        let root = base("");
+        assert_eq!(
+            Some("/alice/game/tree/docs/setup.md?branch=v1%2E0"),
+            root.resolve("docs/setup.md", false).as_deref()
+        );
+        assert_eq!(
+            Some("/alice/game/raw/screenshots/title.png?branch=v1%2E0"),
+            root.resolve("./screenshots/title.png", true).as_deref()
+        );
+        assert_eq!(
+            Some("/alice/game/tree/?branch=v1%2E0"),
+            root.resolve("../..", false).as_deref()
+        );
+
+        let docs = base("docs/guide");
+        assert_eq!(
+            Some("/alice/game/tree/docs/intro.md?branch=v1%2E0#usage"),
+            docs.resolve("../intro.md?plain=1#usage", false).as_deref()
+        );
+        assert_eq!(
+            Some("/alice/game/tree/src/main.rs?branch=v1%2E0#L10"),
+            docs.resolve("../../src/main.rs#L10", false).as_deref()
+        );
+        assert_eq!(
+            Some("/alice/game/tree/docs/guide/notes/a:b.md?branch=v1%2E0"),
+            docs.resolve("notes/a:b.md", false).as_deref()
+        );
+    }
+
+    #[test]
+    fn absolute_links() {
+        let root = base("docs");
+        assert_eq!(None, root.resolve("https://example.com", false));
+        assert_eq!(None, root.resolve("mailto:alice@example.com", false));
+        assert_eq!(None, root.resolve("/bob/other", false));
+        assert_eq!(None, root.resolve("//example.com/logo.png", true));
+        assert_eq!(None, root.resolve("#usage", false));
+        assert_eq!(None, root.resolve("", false));
+    }
+
+    #[test]
+    fn heading_anchors() {
+        let document = markdown(
+            "# Game\n\n## Controls\n\n## Controls\n\n### Key `F1`: help! {#help}\n\n\
+             See [controls](#controls) and [Spaß](#spaß).\n\n## Spaß",
+            &base(""),
+        );
+
+        assert_eq!(
+            "<h1 id=\"game\">Game<a class=\"heading-anchor\" href=\"#game\" \
+             rel=\"noopener noreferrer\">#</a></h1>\n\
+             <h2 id=\"controls\">Controls<a class=\"heading-anchor\" href=\"#controls\" \
+             rel=\"noopener noreferrer\">#</a></h2>\n\
+             <h2 id=\"controls-1\">Controls<a class=\"heading-anchor\" href=\"#controls-1\" \
+             rel=\"noopener noreferrer\">#</a></h2>\n\
+             <h3 id=\"help\">Key <code>F1</code>: help!<a class=\"heading-anchor\" \
+             href=\"#help\" rel=\"noopener noreferrer\">#</a></h3>\n\
+             <p>See <a href=\"#controls\" rel=\"noopener noreferrer\">controls</a> and \
+             <a href=\"#spa%C3%9F\" rel=\"noopener noreferrer\">Spaß</a>.</p>\n\
+             <h2 id=\"spaß\">Spaß<a class=\"heading-anchor\" href=\"#spa%C3%9F\" \
+             rel=\"noopener noreferrer\">#</a></h2>\n",
+            document.html
+        );
+        assert_eq!(
+            vec![
+                (1, "game", "Game"),
+                (2, "controls", "Controls"),
+                (2, "controls-1", "Controls"),
+                (3, "help", "Key F1: help!"),
+                (2, "spaß", "Spaß"),
+            ],
+            document
+                .toc
+                .iter()
+                .map(|h| (h.level, h.id.as_str(), h.title.as_str()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("key-f1-help", slugify("Key F1: help!"));
+        assert_eq!("spaß_im-spiel", slugify(" Spaß_im Spiel "));
+        assert_eq!("section", slugify("!!!"));
+
+        // Explicit IDs are unique as well, whether they clash with each other or generated ones.
+        let ids = markdown("# Help {#help}\n\n# Help\n\n# Other {#help}", &base(""))
+            .toc
+            .into_iter()
+            .map(|h| h.id)
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["help", "help-1", "help-2"], ids);
+    }
+
+    #[test]
+    fn sanitized_html() {
+        let html = markdown(
+            "<details open><summary>Info</summary>\n\n*text*\n\n</details>\n\n\
+             <p align=\"center\"><img src=\"logo.png\" width=\"64\" onerror=\"alert(1)\"></p>\n\n\
+             Press <kbd>F1</kbd><script>alert(1)</script><iframe src=\"https://example.com\"></iframe>\n\n\
+             <a id=\"navbar\" href=\"#navbar\">Top</a>",
+            &base("docs"),
+        )
+        .html;
+
+        assert_eq!(
+            "<details open=\"\"><summary>Info</summary>\n<p><em>text</em></p>\n</details>\n\
+             <p align=\"center\"><img src=\"/alice/game/raw/docs/logo.png?branch=v1%2E0\" \
+             width=\"64\"></p>\n<p>Press <kbd>F1</kbd></p>\n\
+             <p><a id=\"user-content-navbar\" href=\"#user-content-navbar\" \
+             rel=\"noopener noreferrer\">Top</a></p>\n",
+            html
+        );
+    }
+}
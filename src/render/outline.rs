@@ -0,0 +1,114 @@
+//! Extraction of an outline of the definitions in a source file, based on the scopes that syntaxes
+//! assign to the names of functions, classes and the like.
+
+use std::sync::LazyLock;
+
+use syntect::parsing::{Scope, ScopeStack, ScopeStackOp};
+
+use crate::models::{Symbol, SymbolKind};
+
+/// Scopes of names that are part of the outline, following the scope naming guidelines of Sublime
+/// Text. Others like `entity.name.tag` or `entity.name.label` are too noisy to be of any help.
+static SYMBOL_SCOPES: LazyLock<Vec<(Scope, SymbolKind)>> = LazyLock::new(|| {
+    [
+        ("entity.name.function", SymbolKind::Function),
+        ("entity.name.class", SymbolKind::Type),
+        ("entity.name.struct", SymbolKind::Type),
+        ("entity.name.enum", SymbolKind::Type),
+        ("entity.name.union", SymbolKind::Type),
+        ("entity.name.trait", SymbolKind::Type),
+        ("entity.name.interface", SymbolKind::Type),
+        ("entity.name.impl", SymbolKind::Type),
+        ("entity.name.type", SymbolKind::Type),
+        ("entity.name.namespace", SymbolKind::Module),
+        ("entity.name.module", SymbolKind::Module),
+    ]
+    .into_iter()
+    .map(|(scope, kind)| (Scope::new(scope).unwrap(), kind))
+    .collect()
+});
+
+/// Collects the symbols of a file, one line at a time, from the same parser output that is used
+/// for highlighting.
+#[derive(Default)]
+pub struct Outline {
+    symbols: Vec<Symbol>,
+    /// Symbol whose name is currently being collected, as names can span several tokens.
+    current: Option<(SymbolKind, String)>,
+}
+
+impl Outline {
+    /// Add the symbols from a single line. The `stack` must be the scope stack at the start of the
+    /// line, before applying the `ops` to it.
+    pub fn add_line(
+        &mut self,
+        number: usize,
+        line: &str,
+        ops: &[(usize, ScopeStackOp)],
+        stack: &ScopeStack,
+    ) -> Result<(), syntect::parsing::ScopeError> {
+        let mut stack = stack.clone();
+        let mut pos = 0;
+
+        for (index, op) in ops {
+            self.add_text(number, &line[pos..*index], &stack);
+            pos = *index;
+
+            // Two names right next to each other must not be merged into one.
+            if let ScopeStackOp::Push(scope) = op
+                && symbol_kind(*scope).is_some()
+            {
+                self.finish(number);
+            }
+
+            stack.apply(op)?;
+        }
+
+        self.add_text(number, &line[pos..], &stack);
+        // Names never continue on the next line.
+        self.finish(number);
+
+        Ok(())
+    }
+
+    pub fn finish_outline(self) -> Vec<Symbol> {
+        self.symbols
+    }
+
+    fn add_text(&mut self, number: usize, text: &str, stack: &ScopeStack) {
+        let kind = stack
+            .as_slice()
+            .iter()
+            .rev()
+            .find_map(|scope| symbol_kind(*scope));
+
+        match (kind, &mut self.current) {
+            (Some(kind), Some((current, name))) if kind == *current => name.push_str(text),
+            (Some(kind), _) => {
+                self.finish(number);
+                self.current = Some((kind, text.to_owned()));
+            }
+            (None, _) => self.finish(number),
+        }
+    }
+
+    fn finish(&mut self, number: usize) {
+        if let Some((kind, name)) = self.current.take() {
+            let name = name.trim();
+            if !name.is_empty() {
+                self.symbols.push(Symbol {
+                    kind,
+                    name: name.to_owned(),
+                    line: number,
+                });
+            }
+        }
+    }
+}
+
+fn symbol_kind(scope: Scope) -> Option<SymbolKind> {
+    SYMBOL_SCOPES
+        .iter()
+        .find(|(prefix, _)| prefix.is_prefix_of(scope))
+        .map(|(_, kind)| *kind)
+}
@@ -1,9 +1,11 @@
 pub use self::{
+    release::ReleaseRepository,
     repo::RepoRepository,
     settings::SettingsRepository,
     user::{CreateUser, UserRepository},
 };
 
+mod release;
 mod repo;
 mod settings;
 mod user;
@@ -0,0 +1,197 @@
+use std::{cmp::Reverse, io::ErrorKind};
+
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use git2::Repository;
+use tokio::{
+    fs,
+    io::{self, AsyncRead},
+};
+use tracing::instrument;
+
+use crate::{
+    dirs::DIRS,
+    models::{Release, ReleaseAsset, ReleaseInfo},
+};
+
+pub struct ReleaseRepository<'a, 'b> {
+    user: &'a str,
+    repo: &'b str,
+}
+
+impl<'a, 'b> ReleaseRepository<'a, 'b> {
+    pub fn for_repo(user: &'a str, repo: &'b str) -> Self {
+        Self { user, repo }
+    }
+
+    /// List all tags of the repository as releases, newest first, together with the release notes
+    /// and assets that were uploaded for them.
+    #[instrument(skip_all)]
+    pub async fn list(&self) -> Result<Vec<Release>> {
+        let repo_git = DIRS.repo_git_dir(self.user, self.repo);
+        let tags = tokio::task::spawn_blocking(move || -> Result<_> {
+            let repo = Repository::open(repo_git).context("failed opening repo")?;
+            let names = repo.tag_names(None).context("failed listing tags")?;
+
+            let mut tags = Vec::new();
+
+            for name in names.iter().flatten() {
+                let object = repo
+                    .revparse_single(&format!("refs/tags/{name}"))
+                    .context("failed finding tag")?;
+                // Tags may also point at trees or blobs, which can't be released.
+                let Ok(commit) = object.peel_to_commit() else {
+                    continue;
+                };
+                let message = object
+                    .as_tag()
+                    .and_then(|tag| tag.message())
+                    .map(|message| message.trim().to_owned())
+                    .filter(|message| !message.is_empty());
+
+                tags.push((
+                    name.to_owned(),
+                    message,
+                    commit.as_object().short_id()?.as_str().unwrap().to_owned(),
+                    commit.time().seconds(),
+                ));
+            }
+
+            tags.sort_by_key(|(_, _, _, time)| Reverse(*time));
+
+            Ok(tags)
+        })
+        .await??;
+
+        let mut releases = Vec::with_capacity(tags.len());
+
+        for (tag, message, commit, _) in tags {
+            let notes = self.load_info(&tag).await?.notes;
+            let assets = self.list_assets(&tag).await?;
+
+            releases.push(Release {
+                tag,
+                message,
+                commit,
+                notes,
+                assets,
+            });
+        }
+
+        Ok(releases)
+    }
+
+    #[instrument(skip_all)]
+    pub async fn tag_exists(&self, tag: &str) -> Result<bool> {
+        let repo_git = DIRS.repo_git_dir(self.user, self.repo);
+        let tag = format!("refs/tags/{tag}");
+
+        tokio::task::spawn_blocking(move || -> Result<_> {
+            let repo = Repository::open(repo_git).context("failed opening repo")?;
+            Ok(repo.find_reference(&tag).is_ok())
+        })
+        .await?
+    }
+
+    #[instrument(skip_all)]
+    pub async fn load_info(&self, tag: &str) -> Result<ReleaseInfo> {
+        match fs::read(DIRS.release_info_file(self.user, self.repo, tag)).await {
+            Ok(data) => serde_json::from_slice(&data).map_err(Into::into),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(ReleaseInfo::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    #[instrument(skip_all)]
+    pub async fn save_info(&self, tag: &str, info: &ReleaseInfo) -> Result<()> {
+        let real_file = DIRS.release_info_file(self.user, self.repo, tag);
+        let temp_file = DIRS.release_info_temp_file(self.user, self.repo, tag);
+
+        fs::create_dir_all(DIRS.release_dir(self.user, self.repo, tag)).await?;
+
+        let buf = serde_json::to_vec_pretty(info)?;
+        fs::write(&temp_file, &buf).await?;
+        fs::rename(temp_file, real_file).await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    pub async fn list_assets(&self, tag: &str) -> Result<Vec<ReleaseAsset>> {
+        let mut entries =
+            match fs::read_dir(DIRS.release_assets_dir(self.user, self.repo, tag)).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+                Err(e) => return Err(e.into()),
+            };
+        let mut assets = Vec::new();
+
+        while let Some(entry) = entries.next_entry().await? {
+            let path = Utf8PathBuf::try_from(entry.path())?;
+            let name = path.file_name().unwrap();
+
+            // Skip any leftover temporary files from interrupted uploads.
+            if name.starts_with('~') {
+                continue;
+            }
+
+            assets.push(ReleaseAsset {
+                name: name.to_owned(),
+                size: entry.metadata().await?.len(),
+            });
+        }
+
+        assets.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(assets)
+    }
+
+    /// Store a new asset for the given tag, replacing any previous asset with the same name. The
+    /// content is first written to a temporary file, so a failed upload never leaves a partial
+    /// asset behind.
+    #[instrument(skip_all)]
+    pub async fn add_asset(
+        &self,
+        tag: &str,
+        name: &str,
+        mut content: impl AsyncRead + Unpin,
+    ) -> Result<()> {
+        let real_file = DIRS.release_asset_file(self.user, self.repo, tag, name);
+        let temp_file = DIRS.release_asset_temp_file(self.user, self.repo, tag, name);
+
+        fs::create_dir_all(DIRS.release_assets_dir(self.user, self.repo, tag)).await?;
+
+        let mut file = fs::File::create(&temp_file).await?;
+        if let Err(e) = io::copy(&mut content, &mut file).await {
+            drop(file);
+            fs::remove_file(&temp_file).await.ok();
+            return Err(e).context("failed writing release asset");
+        }
+
+        file.sync_all().await?;
+        fs::rename(temp_file, real_file).await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip_all)]
+    pub async fn delete_asset(&self, tag: &str, name: &str) -> Result<bool> {
+        match fs::remove_file(DIRS.release_asset_file(self.user, self.repo, tag, name)).await {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    #[instrument(skip_all)]
+    pub async fn open_asset(&self, tag: &str, name: &str) -> Result<Option<(fs::File, u64)>> {
+        match fs::File::open(DIRS.release_asset_file(self.user, self.repo, tag, name)).await {
+            Ok(file) => {
+                let size = file.metadata().await?.len();
+                Ok(Some((file, size)))
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
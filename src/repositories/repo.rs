@@ -1,17 +1,40 @@
-use std::{borrow::ToOwned, io::BufRead, str};
+use std::{
+    borrow::ToOwned,
+    collections::{HashMap, HashSet},
+    io::BufRead,
+    path::PathBuf,
+    str,
+    sync::{Arc, LazyLock, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
-use anyhow::{Context, Result};
-use camino::Utf8Path;
+use anyhow::{Context, Result, bail, ensure};
+use camino::{Utf8Path, Utf8PathBuf};
 use futures_util::FutureExt;
-use git2::{Blob, BranchType, ErrorCode, ObjectType, Repository, Tree};
-use tokio::fs;
+use git2::{
+    Blob, BranchType, Commit, Delta, ErrorCode, Index, ObjectType, Oid, Patch, Repository,
+    RepositoryInitOptions, Signature, Sort, Tree, TreeWalkMode, TreeWalkResult,
+};
+use tokio::{
+    fs,
+    sync::{mpsc, oneshot},
+};
 use tracing::instrument;
 
 use crate::{
     dirs::DIRS,
-    models::{FileKind, RepoFile, RepoTree, TreeKind, UserRepo},
+    models::{
+        BranchCreateOutcome, BranchDeleteOutcome, BranchInfo, BranchRenameOutcome, CommitOutcome,
+        Comparison, DiffLine, DiffLineKind, DiffStatus, FileDiff, FileKind, LastCommit, MediaKind,
+        MergeOutcome, MergeStrategy, RawBlob, Readme, ReadmeFormat, RepoFile, RepoTree, SearchFile,
+        SearchLine, SearchResults, TagCreateOutcome, TagDeleteOutcome, TreeKind, UserRepo,
+    },
 };
 
+/// Lock for changes to repo info files, so concurrent changes like recording a push and saving the
+/// settings don't overwrite each other.
+static INFO_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
 pub struct RepoRepository<'a, 'b> {
     user: &'a str,
     repo: &'b str,
@@ -22,6 +45,12 @@ impl<'a, 'b> RepoRepository<'a, 'b> {
         Self { user, repo }
     }
 
+    /// Whether the user may create, update or delete refs of this repo. The same rule applies to
+    /// pushes and to changes from the web interface: only the owner writes to a repo.
+    pub fn can_write_refs(&self, username: &str) -> bool {
+        self.user == username
+    }
+
     #[instrument(skip_all)]
     pub async fn exists(&self) -> bool {
         let (file, git) = tokio::join!(
@@ -41,7 +70,14 @@ impl<'a, 'b> RepoRepository<'a, 'b> {
         Ok(!self.load_info().await?.private)
     }
 
-    pub async fn create(&self, description: String, private: bool) -> Result<bool> {
+    /// Create a new, empty repo. Its `HEAD` points to the given branch, or Git's default branch if
+    /// none is given.
+    pub async fn create(
+        &self,
+        description: String,
+        private: bool,
+        branch: Option<String>,
+    ) -> Result<bool> {
         if self.exists().await {
             return Ok(false);
         }
@@ -50,6 +86,8 @@ impl<'a, 'b> RepoRepository<'a, 'b> {
             name: self.repo.to_owned(),
             description,
             private,
+            pushed: None,
+            template: false,
         })?;
 
         fs::create_dir_all(DIRS.repo_dir(self.user, self.repo))
@@ -65,13 +103,93 @@ impl<'a, 'b> RepoRepository<'a, 'b> {
 
         let repo_git = DIRS.repo_git_dir(self.user, self.repo);
         tokio::task::spawn_blocking(move || {
-            Repository::init_bare(repo_git).context("failed initializing bare repo")
+            let mut options = RepositoryInitOptions::new();
+            options.bare(true);
+
+            if let Some(branch) = &branch {
+                options.initial_head(branch);
+            }
+
+            Repository::init_opts(repo_git, &options).context("failed initializing bare repo")
         })
         .await??;
 
         Ok(true)
     }
 
+    /// Create the initial commit of a new and still empty repo, on its default branch.
+    #[instrument(skip_all)]
+    pub async fn commit_initial(
+        &self,
+        files: Vec<(Utf8PathBuf, Vec<u8>)>,
+        message: &str,
+        author: &str,
+    ) -> Result<()> {
+        let repo_git = DIRS.repo_git_dir(self.user, self.repo);
+        let message = message.to_owned();
+        let author = author.to_owned();
+
+        tokio::task::spawn_blocking(move || -> Result<_> {
+            let repo = Repository::open(repo_git).context("failed opening repo")?;
+            let mut tree = None;
+
+            for (path, content) in files {
+                let components = path_components(&path)?;
+                let blob = repo.blob(&content).context("failed writing blob")?;
+                let id = insert_blob(&repo, tree.as_ref(), &components, blob)?;
+                tree = Some(repo.find_tree(id)?);
+            }
+
+            let tree = tree.context("no files to commit")?;
+            let signature = user_signature(&author)?;
+
+            // Committing to the unborn `HEAD` creates the default branch. This fails if the repo
+            // already has commits, as they'd be lost otherwise.
+            repo.commit(Some("HEAD"), &signature, &signature, &message, &tree, &[])
+                .context("failed creating initial commit")?;
+
+            Ok(())
+        })
+        .await??;
+
+        self.mark_pushed().await
+    }
+
+    /// Fill a new and still empty repo with all branches of another repo and use the same default
+    /// branch.
+    #[instrument(skip_all, fields(%user, %repo))]
+    pub async fn copy_from(&self, user: &str, repo: &str) -> Result<()> {
+        let repo_git = DIRS.repo_git_dir(self.user, self.repo);
+        let source_git = DIRS.repo_git_dir(user, repo);
+
+        let copied = tokio::task::spawn_blocking(move || -> Result<_> {
+            let repo = Repository::open(&repo_git).context("failed opening repo")?;
+            let source = Repository::open(&source_git).context("failed opening source repo")?;
+
+            repo.remote_anonymous(source_git.as_str())
+                .context("failed creating remote")?
+                .fetch(&["+refs/heads/*:refs/heads/*"], None, None)
+                .context("failed fetching branches")?;
+
+            if let Some(head) = source
+                .find_reference("HEAD")
+                .context("failed finding source head")?
+                .symbolic_target()
+            {
+                repo.set_head(head).context("failed setting head")?;
+            }
+
+            Ok(repo.branches(Some(BranchType::Local))?.next().is_some())
+        })
+        .await??;
+
+        if copied {
+            self.mark_pushed().await?;
+        }
+
+        Ok(())
+    }
+
     pub async fn delete(&self) -> Result<bool> {
         if !self.exists().await {
             return Ok(false);
@@ -114,6 +232,268 @@ impl<'a, 'b> RepoRepository<'a, 'b> {
         Ok(branches)
     }
 
+    /// List all branches, with the amount of commits they are ahead and behind of the default
+    /// branch.
+    #[instrument(skip_all)]
+    pub async fn list_branch_info(&self) -> Result<Vec<BranchInfo>> {
+        if !self.exists().await {
+            return Ok(Vec::new());
+        }
+
+        let repo_git = DIRS.repo_git_dir(self.user, self.repo);
+        let branches = tokio::task::spawn_blocking(move || -> Result<_> {
+            let repo = Repository::open(repo_git).context("failed opening repo")?;
+            let head = get_head_commit(&repo)
+                .context("failed getting head commit")?
+                .map(|commit| commit.id());
+
+            repo.branches(Some(BranchType::Local))
+                .context("failed listing branches")?
+                .map(|branch| {
+                    let (branch, _) = branch.context("failed iterating branches")?;
+                    let name = branch
+                        .name()
+                        .context("failed getting branch name")?
+                        .unwrap()
+                        .to_owned();
+
+                    let (ahead, behind) = match (branch.get().target(), head) {
+                        (Some(target), Some(head)) => repo
+                            .graph_ahead_behind(target, head)
+                            .context("failed counting commits")?,
+                        _ => (0, 0),
+                    };
+
+                    Ok(BranchInfo {
+                        name,
+                        ahead,
+                        behind,
+                    })
+                })
+                .collect()
+        })
+        .await??;
+
+        Ok(branches)
+    }
+
+    /// Create a new branch, pointing to the commit that the revspec resolves to.
+    #[instrument(skip_all, fields(%name, %rev))]
+    pub async fn create_branch(&self, name: &str, rev: &str) -> Result<BranchCreateOutcome> {
+        if !self.exists().await {
+            return Ok(BranchCreateOutcome::UnknownRev);
+        }
+
+        let repo_git = DIRS.repo_git_dir(self.user, self.repo);
+        let name = name.to_owned();
+        let rev = rev.to_owned();
+
+        let outcome = tokio::task::spawn_blocking(move || -> Result<_> {
+            let repo = Repository::open(repo_git).context("failed opening repo")?;
+            let Some(commit) = get_rev_commit(&repo, &rev).context("failed getting rev commit")?
+            else {
+                return Ok(BranchCreateOutcome::UnknownRev);
+            };
+
+            match repo.branch(&name, &commit, false) {
+                Ok(_) => Ok(BranchCreateOutcome::Created),
+                Err(e) if e.code() == ErrorCode::Exists => Ok(BranchCreateOutcome::AlreadyExists),
+                Err(e) => Err(e.into()),
+            }
+        })
+        .await??;
+
+        Ok(outcome)
+    }
+
+    /// Rename a branch. If it's the default branch, the repo's `HEAD` follows the new name.
+    #[instrument(skip_all, fields(%name, %new_name))]
+    pub async fn rename_branch(&self, name: &str, new_name: &str) -> Result<BranchRenameOutcome> {
+        if !self.exists().await {
+            return Ok(BranchRenameOutcome::NotFound);
+        }
+
+        let is_default = self.get_branch().await? == name;
+        let repo_git = DIRS.repo_git_dir(self.user, self.repo);
+        let name = name.to_owned();
+        let new_name = new_name.to_owned();
+
+        let outcome = tokio::task::spawn_blocking(move || -> Result<_> {
+            let repo = Repository::open(repo_git).context("failed opening repo")?;
+            let mut branch = match repo.find_branch(&name, BranchType::Local) {
+                Ok(branch) => branch,
+                Err(e) if matches!(e.code(), ErrorCode::NotFound | ErrorCode::InvalidSpec) => {
+                    return Ok(BranchRenameOutcome::NotFound);
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            match branch.rename(&new_name, false) {
+                Ok(_) => {}
+                Err(e) if e.code() == ErrorCode::Exists => {
+                    return Ok(BranchRenameOutcome::AlreadyExists);
+                }
+                Err(e) => return Err(e.into()),
+            }
+
+            if is_default {
+                repo.set_head(&format!("refs/heads/{new_name}"))
+                    .context("failed setting head")?;
+            }
+
+            Ok(BranchRenameOutcome::Renamed)
+        })
+        .await??;
+
+        Ok(outcome)
+    }
+
+    /// Delete a branch, no matter whether it was merged. The default branch can't be deleted.
+    #[instrument(skip_all, fields(%name))]
+    pub async fn delete_branch(&self, name: &str) -> Result<BranchDeleteOutcome> {
+        if !self.exists().await {
+            return Ok(BranchDeleteOutcome::NotFound);
+        }
+        if self.get_branch().await? == name {
+            return Ok(BranchDeleteOutcome::IsDefault);
+        }
+
+        let repo_git = DIRS.repo_git_dir(self.user, self.repo);
+        let name = name.to_owned();
+
+        let outcome = tokio::task::spawn_blocking(move || -> Result<_> {
+            let repo = Repository::open(repo_git).context("failed opening repo")?;
+            let mut branch = match repo.find_branch(&name, BranchType::Local) {
+                Ok(branch) => branch,
+                Err(e) if matches!(e.code(), ErrorCode::NotFound | ErrorCode::InvalidSpec) => {
+                    return Ok(BranchDeleteOutcome::NotFound);
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            branch.delete().context("failed deleting branch")?;
+
+            Ok(BranchDeleteOutcome::Deleted)
+        })
+        .await??;
+
+        Ok(outcome)
+    }
+
+    #[instrument(skip_all)]
+    pub async fn list_tags(&self) -> Result<Vec<String>> {
+        if !self.exists().await {
+            return Ok(Vec::new());
+        }
+
+        let repo_git = DIRS.repo_git_dir(self.user, self.repo);
+        let tags = tokio::task::spawn_blocking(move || -> Result<_> {
+            let repo = Repository::open(repo_git).context("failed opening repo")?;
+            let tags = repo.tag_names(None).context("failed listing tags")?;
+
+            Ok(tags.iter().flatten().map(ToOwned::to_owned).collect())
+        })
+        .await??;
+
+        Ok(tags)
+    }
+
+    /// Create a tag at the commit that the revspec resolves to. With a message the tag is an
+    /// annotated tag object, tagged by the given user, otherwise it's a lightweight tag.
+    #[instrument(skip_all, fields(%name, %rev))]
+    pub async fn create_tag(
+        &self,
+        name: &str,
+        rev: &str,
+        message: Option<&str>,
+        author: &str,
+    ) -> Result<TagCreateOutcome> {
+        if !self.exists().await {
+            return Ok(TagCreateOutcome::UnknownRev);
+        }
+
+        let repo_git = DIRS.repo_git_dir(self.user, self.repo);
+        let name = name.to_owned();
+        let rev = rev.to_owned();
+        let message = message.map(ToOwned::to_owned);
+        let author = author.to_owned();
+
+        let outcome = tokio::task::spawn_blocking(move || -> Result<_> {
+            let repo = Repository::open(repo_git).context("failed opening repo")?;
+            let Some(commit) = get_rev_commit(&repo, &rev).context("failed getting rev commit")?
+            else {
+                return Ok(TagCreateOutcome::UnknownRev);
+            };
+
+            let result = match message {
+                Some(message) => repo.tag(
+                    &name,
+                    commit.as_object(),
+                    &user_signature(&author)?,
+                    &message,
+                    false,
+                ),
+                None => repo.tag_lightweight(&name, commit.as_object(), false),
+            };
+
+            match result {
+                Ok(_) => Ok(TagCreateOutcome::Created),
+                Err(e) if e.code() == ErrorCode::Exists => Ok(TagCreateOutcome::AlreadyExists),
+                Err(e) => Err(e.into()),
+            }
+        })
+        .await??;
+
+        Ok(outcome)
+    }
+
+    /// Delete a tag. Release notes and assets of the tag are kept, the same as when the tag is
+    /// deleted by a push.
+    #[instrument(skip_all, fields(%name))]
+    pub async fn delete_tag(&self, name: &str) -> Result<TagDeleteOutcome> {
+        if !self.exists().await {
+            return Ok(TagDeleteOutcome::NotFound);
+        }
+
+        let repo_git = DIRS.repo_git_dir(self.user, self.repo);
+        let name = name.to_owned();
+
+        let outcome = tokio::task::spawn_blocking(move || -> Result<_> {
+            let repo = Repository::open(repo_git).context("failed opening repo")?;
+
+            match repo.tag_delete(&name) {
+                Ok(()) => Ok(TagDeleteOutcome::Deleted),
+                Err(e) if matches!(e.code(), ErrorCode::NotFound | ErrorCode::InvalidSpec) => {
+                    Ok(TagDeleteOutcome::NotFound)
+                }
+                Err(e) => Err(e.into()),
+            }
+        })
+        .await??;
+
+        Ok(outcome)
+    }
+
+    /// Resolve any revspec to the full ID of the commit it points to.
+    #[instrument(skip_all)]
+    pub async fn resolve_commit(&self, rev: &str) -> Result<Option<String>> {
+        if !self.exists().await {
+            return Ok(None);
+        }
+
+        let repo_git = DIRS.repo_git_dir(self.user, self.repo);
+        let rev = rev.to_owned();
+        let id = tokio::task::spawn_blocking(move || -> Result<_> {
+            let repo = Repository::open(repo_git).context("failed opening repo")?;
+            let commit = get_rev_commit(&repo, &rev).context("failed getting rev commit")?;
+
+            Ok(commit.map(|commit| commit.id().to_string()))
+        })
+        .await??;
+
+        Ok(id)
+    }
+
     #[instrument(skip_all)]
     pub async fn get_branch(&self) -> Result<String> {
         if !self.exists().await {
@@ -175,37 +555,20 @@ impl<'a, 'b> RepoRepository<'a, 'b> {
     }
 
     #[instrument(skip_all)]
-    pub async fn get_readme(&self) -> Result<Option<String>> {
+    pub async fn get_readme(&self) -> Result<Option<Readme>> {
         if !self.exists().await {
             return Ok(None);
         }
 
         let repo_git = DIRS.repo_git_dir(self.user, self.repo);
-        let readme = tokio::task::spawn_blocking(move || -> Result<Option<String>> {
+        let readme = tokio::task::spawn_blocking(move || -> Result<Option<Readme>> {
             let repo = Repository::open(repo_git).context("failed opening repo")?;
             let tree = match get_head_tree(&repo).context("failed getting head commit tree")? {
                 Some(tree) => tree,
                 None => return Ok(None),
             };
-            let entry = tree.iter().find(|entry| {
-                entry.name().is_some_and(|name| {
-                    name.eq_ignore_ascii_case("README.md") || name.eq_ignore_ascii_case("README")
-                }) && entry.kind().is_some_and(|kind| kind == ObjectType::Blob)
-            });
-
-            let content = match entry {
-                Some(entry) => {
-                    let blob = entry
-                        .to_object(&repo)
-                        .context("failed converting entry to object")?
-                        .into_blob()
-                        .unwrap();
-                    str::from_utf8(blob.content()).ok().map(ToOwned::to_owned)
-                }
-                None => None,
-            };
 
-            Ok(content)
+            find_readme(&repo, &tree)
         })
         .await??;
 
@@ -222,13 +585,14 @@ impl<'a, 'b> RepoRepository<'a, 'b> {
 
         let list = tokio::task::spawn_blocking(move || -> Result<_> {
             let repo = Repository::open(repo_git).context("failed opening repo")?;
-            let tree = get_head_tree(&repo).context("failed getting head commit tree")?;
-            let tree = match tree {
-                Some(tree) => tree,
+            let commit = get_head_commit(&repo).context("failed getting head commit")?;
+            let commit = match commit {
+                Some(commit) => commit,
                 None => return Ok(Vec::new()),
             };
+            let tree = commit.tree().context("failed getting head commit tree")?;
 
-            Ok(repo_files_from_tree(&tree))
+            repo_files_from_tree(&repo, &commit, None, &tree)
         })
         .await??;
 
@@ -238,7 +602,7 @@ impl<'a, 'b> RepoRepository<'a, 'b> {
     #[instrument(skip_all)]
     pub async fn get_tree_list(
         &self,
-        branch: &str,
+        rev: &str,
         path: Option<&Utf8Path>,
     ) -> Result<Option<RepoTree>> {
         if !self.exists().await {
@@ -246,28 +610,57 @@ impl<'a, 'b> RepoRepository<'a, 'b> {
         }
 
         let repo_git = DIRS.repo_git_dir(self.user, self.repo);
-        let branch = branch.to_owned();
+        let rev = rev.to_owned();
         let path = path.map(ToOwned::to_owned);
 
         let list = tokio::task::spawn_blocking(move || -> Result<_> {
             let repo = Repository::open(repo_git).context("failed opening repo")?;
-            let tree = match get_branch_tree(&repo, &branch)
-                .context("failed getting branch commit tree")?
-            {
-                Some(tree) => tree,
+            let commit = match get_rev_commit(&repo, &rev).context("failed getting rev commit")? {
+                Some(commit) => commit,
                 None => return Ok(None),
             };
+            let tree = commit.tree().context("failed getting rev commit tree")?;
 
             let tree = match path {
                 Some(path) => match tree.get_path(path.as_std_path()) {
+                    // Submodule commits live in a different repository and can't be shown.
+                    Ok(entry) if entry.kind() == Some(ObjectType::Commit) => return Ok(None),
                     Ok(entry) => {
                         let name = entry.name().unwrap();
+
+                        if entry.kind() == Some(ObjectType::Blob) {
+                            let (size, _) = repo
+                                .odb()
+                                .and_then(|odb| odb.read_header(entry.id()))
+                                .context("failed reading blob header")?;
+
+                            // Media is embedded through the raw endpoint, so the content
+                            // doesn't have to be loaded here at all.
+                            let kind = if let Some((kind, _)) = MediaKind::detect(name) {
+                                Some(TreeKind::Media {
+                                    kind,
+                                    size: size as u64,
+                                })
+                            } else if size > BLOB_LOAD_LIMIT {
+                                Some(TreeKind::TooLarge(size as u64))
+                            } else {
+                                None
+                            };
+
+                            if let Some(kind) = kind {
+                                return Ok(Some(RepoTree {
+                                    name: name.to_owned(),
+                                    kind,
+                                }));
+                            }
+                        }
+
                         let object = entry
                             .to_object(&repo)
                             .context("failed converting tree entry into an object")?;
 
                         if let Some(tree) = object.as_tree() {
-                            repo_tree_from_tree(name, tree)
+                            repo_tree_from_tree(&repo, &commit, &path, name, tree)?
                         } else if let Some(blob) = object.as_blob() {
                             repo_tree_from_blob(name, blob)
                         } else {
@@ -279,7 +672,10 @@ impl<'a, 'b> RepoRepository<'a, 'b> {
                 },
                 None => RepoTree {
                     name: "/".to_owned(),
-                    kind: TreeKind::Directory(repo_files_from_tree(&tree)),
+                    kind: TreeKind::Directory {
+                        files: repo_files_from_tree(&repo, &commit, None, &tree)?,
+                        readme: find_readme(&repo, &tree)?,
+                    },
                 },
             };
 
@@ -290,79 +686,1757 @@ impl<'a, 'b> RepoRepository<'a, 'b> {
         Ok(list)
     }
 
+    /// Stream the content of the file at the given path and revision. Once the size of the blob
+    /// is known, `range` may select an inclusive byte range to send instead of the whole blob.
+    ///
+    /// The content is sent in chunks from a blocking task, so it's never copied as a whole and
+    /// reading stops as soon as the receiver is dropped.
     #[instrument(skip_all)]
-    pub async fn load_info(&self) -> Result<UserRepo> {
-        let data = fs::read(DIRS.repo_info_file(self.user, self.repo)).await?;
-        serde_json::from_slice(&data).map_err(Into::into)
-    }
+    pub async fn get_raw_blob(
+        &self,
+        rev: &str,
+        path: &Utf8Path,
+        range: impl FnOnce(u64) -> Option<(u64, u64)> + Send + 'static,
+    ) -> Result<Option<RawBlob>> {
+        if !self.exists().await {
+            return Ok(None);
+        }
 
-    pub async fn save_info(&self, info: &UserRepo) -> Result<()> {
-        let real_file = DIRS.repo_info_file(self.user, self.repo);
-        let temp_file = DIRS.repo_info_temp_file(self.user, self.repo);
+        let repo_git = DIRS.repo_git_dir(self.user, self.repo);
+        let rev = rev.to_owned();
+        let path = path.to_owned();
+        let (info_tx, info_rx) = oneshot::channel();
+        let (chunk_tx, chunk_rx) = mpsc::channel(RAW_BLOB_CHUNKS);
 
-        let buf = serde_json::to_vec_pretty(info)?;
-        fs::write(&temp_file, &buf).await?;
-        fs::rename(temp_file, real_file).await?;
+        let task = tokio::task::spawn_blocking(move || -> Result<_> {
+            let repo = Repository::open(repo_git).context("failed opening repo")?;
+            let Some(blob) = find_raw_blob(&repo, &rev, &path)? else {
+                info_tx.send(None).ok();
+                return Ok(());
+            };
 
-        Ok(())
-    }
-}
+            let content = blob.content();
+            let size = content.len() as u64;
+            let range = range(size);
+            info_tx
+                .send(Some((size, str::from_utf8(content).is_ok(), range)))
+                .ok();
 
-fn get_branch_tree<'a>(repo: &'a Repository, branch: &str) -> Result<Option<Tree<'a>>> {
-    match repo.find_branch(branch, BranchType::Local) {
-        Ok(branch) => match branch.into_reference().peel_to_commit() {
-            Ok(commit) => commit.tree().map(Some).map_err(Into::into),
-            Err(e) => Err(e.into()),
-        },
-        Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
-        Err(e) => Err(e.into()),
-    }
-}
+            let content = match range {
+                Some((start, end)) => &content[usize::try_from(start)?..=usize::try_from(end)?],
+                None => content,
+            };
 
-fn get_head_tree(repo: &Repository) -> Result<Option<Tree<'_>>> {
-    match repo.head() {
-        Ok(head) => match head.peel_to_commit() {
-            Ok(commit) => commit.tree().map(Some).map_err(Into::into),
-            Err(e) if e.code() == ErrorCode::UnbornBranch => Ok(None),
-            Err(e) => Err(e.into()),
-        },
-        Err(e) if e.code() == ErrorCode::UnbornBranch => Ok(None),
-        Err(e) => Err(e.into()),
+            for chunk in content.chunks(RAW_BLOB_CHUNK_SIZE) {
+                // The receiver is gone if the client stopped the download.
+                if chunk_tx.blocking_send(chunk.to_vec()).is_err() {
+                    break;
+                }
+            }
+
+            Ok(())
+        });
+
+        let Ok(info) = info_rx.await else {
+            // The info is only dropped without sending it if the task failed.
+            task.await??;
+            bail!("raw blob task stopped without a result");
+        };
+
+        Ok(info.map(|(size, text, range)| RawBlob {
+            size,
+            text,
+            range,
+            content: chunk_rx,
+        }))
     }
-}
 
-fn repo_files_from_tree(tree: &Tree<'_>) -> Vec<RepoFile> {
-    tree.iter()
-        .filter_map(|entry| {
-            let kind = match entry.kind()? {
-                ObjectType::Tree => FileKind::Directory,
-                ObjectType::Blob => FileKind::File,
-                _ => return None,
+    /// Look up the `linguist-language` attribute of a file, as defined by the `.gitattributes`
+    /// files of the given revision and the repository's `info/attributes` file.
+    #[instrument(skip_all)]
+    pub async fn get_language(&self, rev: &str, path: &Utf8Path) -> Result<Option<String>> {
+        if !self.exists().await {
+            return Ok(None);
+        }
+
+        let repo_git = DIRS.repo_git_dir(self.user, self.repo);
+        let rev = rev.to_owned();
+        let path = path.to_owned();
+
+        let language = tokio::task::spawn_blocking(move || -> Result<_> {
+            let repo = Repository::open(repo_git).context("failed opening repo")?;
+            let tree = match get_rev_tree(&repo, &rev).context("failed getting rev commit tree")? {
+                Some(tree) => tree,
+                None => return Ok(None),
             };
-            let name = entry.name()?.to_owned();
 
-            Some(RepoFile { name, kind })
+            find_attribute(&repo, &tree, &path, "linguist-language")
         })
-        .collect()
-}
+        .await??;
 
-fn repo_tree_from_tree(name: &str, tree: &Tree<'_>) -> RepoTree {
-    RepoTree {
-        name: name.to_owned(),
-        kind: TreeKind::Directory(repo_files_from_tree(tree)),
+        Ok(language)
     }
-}
 
-fn repo_tree_from_blob(name: &str, blob: &Blob<'_>) -> RepoTree {
-    RepoTree {
-        name: name.to_owned(),
-        kind: if blob.is_binary() {
-            TreeKind::Binary(blob.size())
+    /// Search the content of all files in the given revision for the query, ignoring ASCII case.
+    /// Binary and very large files are skipped, and the search stops once it runs into
+    /// [`SEARCH_TIME_LIMIT`] or found [`SEARCH_RESULT_LIMIT`] matching lines.
+    #[instrument(skip_all)]
+    pub async fn search(&self, rev: &str, query: &str) -> Result<Option<SearchResults>> {
+        if !self.exists().await {
+            return Ok(None);
+        }
+
+        let repo_git = DIRS.repo_git_dir(self.user, self.repo);
+        let rev = rev.to_owned();
+        let query = query.to_ascii_lowercase();
+
+        let results = tokio::task::spawn_blocking(move || -> Result<_> {
+            let repo = Repository::open(repo_git).context("failed opening repo")?;
+            let tree = match get_rev_tree(&repo, &rev).context("failed getting rev commit tree")? {
+                Some(tree) => tree,
+                None => return Ok(None),
+            };
+            let odb = repo.odb().context("failed opening object database")?;
+
+            let started = Instant::now();
+            let mut results = SearchResults::default();
+            let mut matches = 0;
+            let mut error = None;
+
+            let walked = tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+                if started.elapsed() > SEARCH_TIME_LIMIT {
+                    results.truncated = true;
+                    return TreeWalkResult::Abort;
+                }
+                if entry.kind() != Some(ObjectType::Blob) {
+                    return TreeWalkResult::Ok;
+                }
+
+                let blob = match odb.read_header(entry.id()) {
+                    Ok((size, _)) if size > SEARCH_BLOB_LIMIT => return TreeWalkResult::Ok,
+                    Ok(_) => repo.find_blob(entry.id()),
+                    Err(e) => Err(e),
+                };
+                let blob = match blob {
+                    Ok(blob) => blob,
+                    Err(e) => {
+                        error = Some(e);
+                        return TreeWalkResult::Abort;
+                    }
+                };
+
+                if blob.is_binary() {
+                    return TreeWalkResult::Ok;
+                }
+                let Ok(content) = str::from_utf8(blob.content()) else {
+                    return TreeWalkResult::Ok;
+                };
+
+                let hunks = search_content(content, &query, SEARCH_RESULT_LIMIT - matches);
+                if !hunks.is_empty() {
+                    matches += hunks.iter().flatten().filter(|line| line.matched).count();
+                    results.files.push(SearchFile {
+                        path: format!("{dir}{}", entry.name().unwrap_or_default()),
+                        hunks,
+                    });
+
+                    if matches >= SEARCH_RESULT_LIMIT {
+                        results.truncated = true;
+                        return TreeWalkResult::Abort;
+                    }
+                }
+
+                TreeWalkResult::Ok
+            });
+
+            if let Some(e) = error {
+                return Err(e).context("failed loading blob");
+            }
+            // Aborting the walk is reported as user error.
+            if let Err(e) = walked
+                && e.code() != ErrorCode::User
+            {
+                return Err(e.into());
+            }
+
+            Ok(Some(results))
+        })
+        .await??;
+
+        Ok(results)
+    }
+
+    /// Write the given files to a branch as a single new commit, authored by the given user. The
+    /// branch is only updated if it still points to the `parent` commit that the changes are based
+    /// on, so changes that happened in the meantime are never overwritten.
+    ///
+    /// Files that are tracked by Git LFS according to the `.gitattributes` are refused, as there is
+    /// no LFS server that could hand out their content again. All other files must stay within the
+    /// [`COMMIT_BLOB_LIMIT`].
+    #[instrument(skip_all, fields(%branch, %parent))]
+    pub async fn commit_files(
+        &self,
+        branch: &str,
+        parent: &str,
+        files: Vec<(Utf8PathBuf, Vec<u8>)>,
+        message: &str,
+        author: &str,
+    ) -> Result<CommitOutcome> {
+        if !self.exists().await {
+            return Ok(CommitOutcome::UnknownBranch);
+        }
+
+        let repo_git = DIRS.repo_git_dir(self.user, self.repo);
+        let branch = format!("refs/heads/{branch}");
+        // Changes based on something that isn't even a commit ID can't be based on the branch.
+        let Ok(parent) = Oid::from_str(parent) else {
+            return Ok(CommitOutcome::BranchMoved);
+        };
+        let message = message.to_owned();
+        let author = author.to_owned();
+
+        let outcome = tokio::task::spawn_blocking(move || -> Result<_> {
+            let repo = Repository::open(repo_git).context("failed opening repo")?;
+
+            let target = match repo.find_reference(&branch) {
+                Ok(reference) => reference.target(),
+                Err(e) if matches!(e.code(), ErrorCode::NotFound | ErrorCode::InvalidSpec) => {
+                    return Ok(CommitOutcome::UnknownBranch);
+                }
+                Err(e) => return Err(e.into()),
+            };
+            if target != Some(parent) {
+                return Ok(CommitOutcome::BranchMoved);
+            }
+
+            let parent = repo.find_commit(parent).context("failed finding parent")?;
+            let mut tree = parent.tree().context("failed getting parent tree")?;
+
+            for (path, content) in files {
+                let Ok(components) = path_components(&path) else {
+                    return Ok(CommitOutcome::InvalidPath(path.into_string()));
+                };
+                if path_blocked(&tree, &components) {
+                    return Ok(CommitOutcome::InvalidPath(path.into_string()));
+                }
+                if find_attribute(&repo, &tree, &path, "filter")?.as_deref() == Some("lfs") {
+                    return Ok(CommitOutcome::LfsTracked(path.into_string()));
+                }
+                if content.len() > COMMIT_BLOB_LIMIT {
+                    return Ok(CommitOutcome::TooLarge(path.into_string()));
+                }
+
+                let blob = repo.blob(&content).context("failed writing blob")?;
+                let id = insert_blob(&repo, Some(&tree), &components, blob)?;
+                tree = repo.find_tree(id)?;
+            }
+
+            if tree.id() == parent.tree_id() {
+                return Ok(CommitOutcome::Unchanged);
+            }
+
+            let signature = user_signature(&author)?;
+            let commit = repo
+                .commit(None, &signature, &signature, &message, &tree, &[&parent])
+                .context("failed creating commit")?;
+
+            // Compare and swap, in case the branch was updated by a push while committing.
+            match repo.reference_matching(
+                &branch,
+                commit,
+                true,
+                parent.id(),
+                &format!(
+                    "commit (web): {}",
+                    message.lines().next().unwrap_or_default()
+                ),
+            ) {
+                Ok(_) => Ok(CommitOutcome::Committed(commit.to_string())),
+                Err(e) if e.code() == ErrorCode::Modified => Ok(CommitOutcome::BranchMoved),
+                Err(e) => Err(e.into()),
+            }
+        })
+        .await??;
+
+        if matches!(outcome, CommitOutcome::Committed(_)) {
+            self.mark_pushed().await?;
+        }
+
+        Ok(outcome)
+    }
+
+    /// Compare the `head` revision against the `base` revision, with the commits and changes that
+    /// merging the head would bring into the base.
+    #[instrument(skip_all, fields(%base, %head))]
+    pub async fn compare(&self, base: &str, head: &str) -> Result<Option<Comparison>> {
+        if !self.exists().await {
+            return Ok(None);
+        }
+
+        let repo_git = DIRS.repo_git_dir(self.user, self.repo);
+        let base = base.to_owned();
+        let head = head.to_owned();
+
+        let comparison = tokio::task::spawn_blocking(move || -> Result<_> {
+            let repo = Repository::open(repo_git).context("failed opening repo")?;
+            compare_revs(&repo, &base, &head)
+        })
+        .await??;
+
+        Ok(comparison)
+    }
+
+    /// Merge the `head_commit` into the `base` branch with the given strategy, authored by the
+    /// given user. Like when committing files, the branch is only updated if it still points to
+    /// the `base_commit`, so exactly what was reviewed is merged. The `head` name is only used for
+    /// the commit message.
+    #[instrument(skip_all, fields(%base, %head, ?strategy))]
+    pub async fn merge(
+        &self,
+        base: &str,
+        base_commit: &str,
+        head: &str,
+        head_commit: &str,
+        strategy: MergeStrategy,
+        author: &str,
+    ) -> Result<MergeOutcome> {
+        if !self.exists().await {
+            return Ok(MergeOutcome::UnknownBranch);
+        }
+
+        let repo_git = DIRS.repo_git_dir(self.user, self.repo);
+        let Ok(base_commit) = Oid::from_str(base_commit) else {
+            return Ok(MergeOutcome::BranchMoved);
+        };
+        let Ok(head_commit) = Oid::from_str(head_commit) else {
+            return Ok(MergeOutcome::UnknownBranch);
+        };
+        let base = base.to_owned();
+        let head = head.to_owned();
+        let author = author.to_owned();
+
+        let outcome = tokio::task::spawn_blocking(move || -> Result<_> {
+            let repo = Repository::open(repo_git).context("failed opening repo")?;
+            merge_into(
+                &repo,
+                (&base, base_commit),
+                (&head, head_commit),
+                strategy,
+                &author,
+            )
+        })
+        .await??;
+
+        if outcome == MergeOutcome::Merged {
+            self.mark_pushed().await?;
+        }
+
+        Ok(outcome)
+    }
+
+    #[instrument(skip_all)]
+    pub async fn load_info(&self) -> Result<UserRepo> {
+        let data = fs::read(DIRS.repo_info_file(self.user, self.repo)).await?;
+        serde_json::from_slice(&data).map_err(Into::into)
+    }
+
+    /// Record the current time as time of the last push.
+    #[instrument(skip_all)]
+    pub async fn mark_pushed(&self) -> Result<()> {
+        let pushed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)?
+            .as_secs()
+            .try_into()?;
+
+        self.update_info(|info| info.pushed = Some(pushed)).await
+    }
+
+    /// Load, change and save the repo info while holding the [`INFO_LOCK`].
+    pub async fn update_info(&self, update: impl FnOnce(&mut UserRepo)) -> Result<()> {
+        let _guard = INFO_LOCK.lock().await;
+        let mut info = self.load_info().await?;
+
+        update(&mut info);
+
+        self.save_info(&info).await
+    }
+
+    pub async fn save_info(&self, info: &UserRepo) -> Result<()> {
+        let real_file = DIRS.repo_info_file(self.user, self.repo);
+        let temp_file = DIRS.repo_info_temp_file(self.user, self.repo);
+
+        let buf = serde_json::to_vec_pretty(info)?;
+        fs::write(&temp_file, &buf).await?;
+        fs::rename(temp_file, real_file).await?;
+
+        Ok(())
+    }
+}
+
+/// Resolve any revspec (branch, tag, full or short commit ID, `main~3`, ...) to the tree of the
+/// commit it points to. Specs that can't be resolved or don't point to a commit yield `None`.
+fn get_rev_tree<'a>(repo: &'a Repository, rev: &str) -> Result<Option<Tree<'a>>> {
+    match get_rev_commit(repo, rev)? {
+        Some(commit) => commit.tree().map(Some).map_err(Into::into),
+        None => Ok(None),
+    }
+}
+
+fn get_rev_commit<'a>(repo: &'a Repository, rev: &str) -> Result<Option<Commit<'a>>> {
+    let commit = repo
+        .revparse_single(rev)
+        .and_then(|object| object.peel_to_commit());
+
+    match commit {
+        Ok(commit) => Ok(Some(commit)),
+        Err(e)
+            if matches!(
+                e.code(),
+                ErrorCode::NotFound
+                    | ErrorCode::InvalidSpec
+                    | ErrorCode::Ambiguous
+                    | ErrorCode::Peel
+            ) =>
+        {
+            Ok(None)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn get_head_commit(repo: &Repository) -> Result<Option<Commit<'_>>> {
+    match repo.head() {
+        Ok(head) => match head.peel_to_commit() {
+            Ok(commit) => Ok(Some(commit)),
+            Err(e) if e.code() == ErrorCode::UnbornBranch => Ok(None),
+            Err(e) => Err(e.into()),
+        },
+        Err(e) if e.code() == ErrorCode::UnbornBranch => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn get_head_tree(repo: &Repository) -> Result<Option<Tree<'_>>> {
+    match get_head_commit(repo)? {
+        Some(commit) => commit.tree().map(Some).map_err(Into::into),
+        None => Ok(None),
+    }
+}
+
+/// File mode of regular, non-executable blobs.
+const FILE_MODE_BLOB: i32 = 0o100_644;
+/// File mode of blobs that are marked as executable.
+const FILE_MODE_EXECUTABLE: i32 = 0o100_755;
+/// File mode of blobs that represent a symbolic link, with the target path as content.
+const FILE_MODE_SYMLINK: i32 = 0o120_000;
+/// File mode of subtrees.
+const FILE_MODE_TREE: i32 = 0o040_000;
+
+/// Maximum size of files that are committed through the web interface. It's the same as the
+/// [`BLOB_LOAD_LIMIT`], so every committed file can still be shown. The upload and edit forms
+/// mention this limit.
+const COMMIT_BLOB_LIMIT: usize = BLOB_LOAD_LIMIT;
+
+/// Compare two revisions, as described in [`RepoRepository::compare`].
+fn compare_revs(repo: &Repository, base: &str, head: &str) -> Result<Option<Comparison>> {
+    let (Some(base), Some(head)) = (
+        get_rev_commit(repo, base).context("failed getting base commit")?,
+        get_rev_commit(repo, head).context("failed getting head commit")?,
+    ) else {
+        return Ok(None);
+    };
+
+    let merge_base = find_merge_base(repo, base.id(), head.id())?;
+    let commits = commits_between(repo, base.id(), head.id())?
+        .iter()
+        .map(last_commit_from_commit)
+        .collect::<Vec<_>>();
+
+    let old_tree = match merge_base {
+        Some(id) => Some(repo.find_commit(id)?.tree()?),
+        None => None,
+    };
+    let (files, truncated) = diff_files(repo, old_tree.as_ref(), &head.tree()?)?;
+
+    let fast_forward = merge_base == Some(base.id());
+    let conflicts = if fast_forward || commits.is_empty() {
+        Vec::new()
+    } else {
+        let index = repo
+            .merge_commits(&base, &head, None)
+            .context("failed merging commits")?;
+        conflict_paths(&index)?
+    };
+
+    Ok(Some(Comparison {
+        base_commit: base.id().to_string(),
+        head_commit: head.id().to_string(),
+        commits,
+        files,
+        truncated,
+        fast_forward,
+        conflicts,
+    }))
+}
+
+/// Merge a head commit into a base branch, as described in [`RepoRepository::merge`]. Both are
+/// given by their name and the ID of the commit that was reviewed.
+fn merge_into(
+    repo: &Repository,
+    (base, base_commit): (&str, Oid),
+    (head, head_commit): (&str, Oid),
+    strategy: MergeStrategy,
+    author: &str,
+) -> Result<MergeOutcome> {
+    let branch = format!("refs/heads/{base}");
+    let summary = if strategy == MergeStrategy::Squash {
+        format!("Squash branch '{head}' into {base}")
+    } else {
+        format!("Merge branch '{head}' into {base}")
+    };
+
+    let target = match repo.find_reference(&branch) {
+        Ok(reference) => reference.target(),
+        Err(e) if matches!(e.code(), ErrorCode::NotFound | ErrorCode::InvalidSpec) => {
+            return Ok(MergeOutcome::UnknownBranch);
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if target != Some(base_commit) {
+        return Ok(MergeOutcome::BranchMoved);
+    }
+
+    let base = repo
+        .find_commit(base_commit)
+        .context("failed finding base")?;
+    let head = match repo.find_commit(head_commit) {
+        Ok(head) => head,
+        Err(e) if e.code() == ErrorCode::NotFound => {
+            return Ok(MergeOutcome::UnknownBranch);
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let merge_base = find_merge_base(repo, base.id(), head.id())?;
+
+    if merge_base == Some(head.id()) {
+        return Ok(MergeOutcome::UpToDate);
+    }
+
+    let commit = if strategy == MergeStrategy::FastForward {
+        if merge_base != Some(base.id()) {
+            return Ok(MergeOutcome::NotFastForward);
+        }
+
+        head.id()
+    } else {
+        let mut index = repo
+            .merge_commits(&base, &head, None)
+            .context("failed merging commits")?;
+        if index.has_conflicts() {
+            return Ok(MergeOutcome::Conflicts);
+        }
+
+        let tree = index.write_tree_to(repo).context("failed writing tree")?;
+        let tree = repo.find_tree(tree)?;
+        let signature = user_signature(author)?;
+
+        if strategy == MergeStrategy::Squash {
+            let mut message = format!("{summary}\n");
+            for commit in commits_between(repo, base.id(), head.id())? {
+                message.push_str("\n* ");
+                message.push_str(commit.summary().unwrap_or_default());
+            }
+
+            repo.commit(None, &signature, &signature, &message, &tree, &[&base])
+        } else {
+            repo.commit(
+                None,
+                &signature,
+                &signature,
+                &summary,
+                &tree,
+                &[&base, &head],
+            )
+        }
+        .context("failed creating commit")?
+    };
+
+    match repo.reference_matching(&branch, commit, true, base.id(), &summary) {
+        Ok(_) => Ok(MergeOutcome::Merged),
+        Err(e) if e.code() == ErrorCode::Modified => Ok(MergeOutcome::BranchMoved),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Find the best common ancestor of two commits, or `None` if their histories are unrelated.
+fn find_merge_base(repo: &Repository, one: Oid, two: Oid) -> Result<Option<Oid>> {
+    match repo.merge_base(one, two) {
+        Ok(id) => Ok(Some(id)),
+        Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Maximum amount of commits that are listed when comparing revisions.
+const COMPARE_COMMIT_LIMIT: usize = 250;
+
+/// Collect the commits that are reachable from `head` but not from `base`, newest first.
+fn commits_between(repo: &Repository, base: Oid, head: Oid) -> Result<Vec<Commit<'_>>> {
+    let mut walk = repo.revwalk().context("failed creating revwalk")?;
+    walk.set_sorting(Sort::TIME)?;
+    walk.push(head)?;
+    walk.hide(base)?;
+
+    walk.take(COMPARE_COMMIT_LIMIT)
+        .map(|id| repo.find_commit(id?).map_err(Into::into))
+        .collect()
+}
+
+/// Maximum amount of diff lines that are shown when comparing revisions, over all files. Files
+/// beyond that are still listed, but without their changes.
+const DIFF_LINE_LIMIT: usize = 5_000;
+
+/// Diff two trees file by file, with renames detected. The returned flag tells whether the lines
+/// of some files were left out, because they'd exceed the [`DIFF_LINE_LIMIT`].
+fn diff_files(
+    repo: &Repository,
+    old: Option<&Tree<'_>>,
+    new: &Tree<'_>,
+) -> Result<(Vec<FileDiff>, bool)> {
+    let mut diff = repo
+        .diff_tree_to_tree(old, Some(new), None)
+        .context("failed diffing trees")?;
+    diff.find_similar(None).context("failed finding renames")?;
+
+    let mut files = Vec::new();
+    let mut remaining = DIFF_LINE_LIMIT;
+    let mut truncated = false;
+
+    for index in 0..diff.deltas().len() {
+        let Some(file_patch) = Patch::from_diff(&diff, index)? else {
+            continue;
+        };
+
+        let delta = file_patch.delta();
+        let status = match delta.status() {
+            Delta::Added | Delta::Copied => DiffStatus::Added,
+            Delta::Deleted => DiffStatus::Deleted,
+            Delta::Renamed => DiffStatus::Renamed,
+            _ => DiffStatus::Modified,
+        };
+        let path_of = |file: git2::DiffFile<'_>| {
+            file.path()
+                .map(|path| path.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+        let path = match status {
+            DiffStatus::Deleted => path_of(delta.old_file()),
+            _ => path_of(delta.new_file()),
+        };
+        let old_path = (status == DiffStatus::Renamed).then(|| path_of(delta.old_file()));
+        let binary = delta.flags().is_binary();
+
+        let (_, additions, deletions) = file_patch.line_stats()?;
+        let mut hunks = Vec::new();
+        let lines = (0..file_patch.num_hunks())
+            .map(|hunk| file_patch.num_lines_in_hunk(hunk))
+            .sum::<Result<usize, _>>()?;
+
+        if lines > remaining {
+            truncated = true;
+        } else if !binary {
+            remaining -= lines;
+
+            for hunk in 0..file_patch.num_hunks() {
+                let mut diff_lines = Vec::new();
+
+                for line in 0..file_patch.num_lines_in_hunk(hunk)? {
+                    let line = file_patch.line_in_hunk(hunk, line)?;
+                    let kind = match line.origin() {
+                        ' ' => DiffLineKind::Context,
+                        '+' => DiffLineKind::Added,
+                        '-' => DiffLineKind::Removed,
+                        // Markers for missing newlines at the end of the file.
+                        _ => continue,
+                    };
+
+                    diff_lines.push(DiffLine {
+                        kind,
+                        old: line.old_lineno(),
+                        new: line.new_lineno(),
+                        content: String::from_utf8_lossy(line.content())
+                            .trim_end_matches(['\n', '\r'])
+                            .to_owned(),
+                    });
+                }
+
+                hunks.push(diff_lines);
+            }
+        }
+
+        files.push(FileDiff {
+            path,
+            old_path,
+            status,
+            additions,
+            deletions,
+            binary,
+            hunks,
+        });
+    }
+
+    Ok((files, truncated))
+}
+
+/// Collect the paths of all conflicting files in the index of a merge.
+fn conflict_paths(index: &Index) -> Result<Vec<String>> {
+    let mut paths = index
+        .conflicts()
+        .context("failed listing conflicts")?
+        .map(|conflict| {
+            let conflict = conflict?;
+            let entry = conflict
+                .our
+                .or(conflict.their)
+                .or(conflict.ancestor)
+                .context("conflict without any entries")?;
+
+            Ok(String::from_utf8_lossy(&entry.path).into_owned())
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    paths.sort();
+    paths.dedup();
+
+    Ok(paths)
+}
+
+/// Users don't have an email address, so commits made through the web interface get a placeholder
+/// address derived from the username.
+fn user_signature(username: &str) -> Result<Signature<'static>> {
+    Signature::now(username, &format!("{username}@marmalade")).map_err(Into::into)
+}
+
+/// Split a path that files are written to into its components, rejecting anything that could
+/// escape the tree or write into the `.git` directory of a checkout.
+fn path_components(path: &Utf8Path) -> Result<Vec<&str>> {
+    let components = path
+        .as_str()
+        .split('/')
+        .map(|name| {
+            ensure!(
+                !matches!(name, "" | "." | "..") && !name.eq_ignore_ascii_case(".git"),
+                "invalid path component `{name}`"
+            );
+            Ok(name)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(components)
+}
+
+/// Find the blob of the file at the given path and revision, if it exists.
+fn find_raw_blob<'r>(repo: &'r Repository, rev: &str, path: &Utf8Path) -> Result<Option<Blob<'r>>> {
+    let Some(tree) = get_rev_tree(repo, rev).context("failed getting rev commit tree")? else {
+        return Ok(None);
+    };
+
+    match tree.get_path(path.as_std_path()) {
+        Ok(entry) if entry.kind() == Some(ObjectType::Blob) => repo
+            .find_blob(entry.id())
+            .map(Some)
+            .context("failed finding blob"),
+        Ok(_) => Ok(None),
+        Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Check whether an existing entry of the tree is in the way of a file at the path, because the
+/// path itself isn't a file or one of its parents isn't a directory.
+fn path_blocked(tree: &Tree<'_>, components: &[&str]) -> bool {
+    let mut path = Utf8PathBuf::new();
+
+    for (i, name) in components.iter().enumerate() {
+        path.push(name);
+
+        let expected = if i + 1 == components.len() {
+            ObjectType::Blob
+        } else {
+            ObjectType::Tree
+        };
+
+        match tree.get_path(path.as_std_path()) {
+            Ok(entry) if entry.kind() == Some(expected) => {}
+            Ok(_) => return true,
+            Err(_) => return false,
+        }
+    }
+
+    false
+}
+
+/// Write the blob into the tree at the given path, creating any missing directories, and return
+/// the ID of the new tree. Replaced files keep their mode, so executables stay executable.
+fn insert_blob(
+    repo: &Repository,
+    tree: Option<&Tree<'_>>,
+    path: &[&str],
+    blob: Oid,
+) -> Result<Oid> {
+    let Some((name, rest)) = path.split_first() else {
+        bail!("empty path");
+    };
+
+    let mut builder = repo
+        .treebuilder(tree)
+        .context("failed creating tree builder")?;
+    let existing = tree.and_then(|tree| tree.get_name(name));
+
+    if rest.is_empty() {
+        let mode = match existing {
+            Some(entry) if entry.kind() == Some(ObjectType::Blob) => entry.filemode(),
+            Some(_) => bail!("`{name}` is not a file"),
+            None => FILE_MODE_BLOB,
+        };
+
+        builder.insert(name, blob, mode)?;
+    } else {
+        let subtree = match existing {
+            Some(entry) if entry.kind() == Some(ObjectType::Tree) => {
+                Some(repo.find_tree(entry.id())?)
+            }
+            Some(_) => bail!("`{name}` is not a directory"),
+            None => None,
+        };
+
+        let id = insert_blob(repo, subtree.as_ref(), rest, blob)?;
+        builder.insert(name, id, FILE_MODE_TREE)?;
+    }
+
+    builder.write().map_err(Into::into)
+}
+
+fn repo_files_from_tree(
+    repo: &Repository,
+    commit: &Commit<'_>,
+    path: Option<&Utf8Path>,
+    tree: &Tree<'_>,
+) -> Result<Vec<RepoFile>> {
+    let last_commits = find_last_commits(repo, commit, path, tree)?;
+    let submodules = if tree
+        .iter()
+        .any(|entry| entry.kind() == Some(ObjectType::Commit))
+    {
+        load_gitmodules(repo, &commit.tree()?)?
+    } else {
+        HashMap::new()
+    };
+    let odb = repo.odb().context("failed opening object database")?;
+
+    tree.iter()
+        .filter_map(|entry| {
+            let name = entry.name()?.to_owned();
+            let file = match entry.kind()? {
+                ObjectType::Tree => RepoFile::new(name, FileKind::Directory),
+                ObjectType::Commit => {
+                    let full_path = match path {
+                        Some(path) => path.join(&name).into_string(),
+                        None => name.clone(),
+                    };
+
+                    RepoFile {
+                        target: submodules.get(&full_path).cloned(),
+                        ..RepoFile::new(name, FileKind::Submodule)
+                    }
+                }
+                ObjectType::Blob => match entry.filemode() {
+                    FILE_MODE_SYMLINK => {
+                        let target = match repo.find_blob(entry.id()) {
+                            Ok(blob) => String::from_utf8_lossy(blob.content()).into_owned(),
+                            Err(e) => return Some(Err(e.into())),
+                        };
+
+                        RepoFile {
+                            target: Some(target),
+                            ..RepoFile::new(name, FileKind::Symlink)
+                        }
+                    }
+                    mode => {
+                        let size = match odb.read_header(entry.id()) {
+                            Ok((size, _)) => size as u64,
+                            Err(e) => return Some(Err(e.into())),
+                        };
+                        let kind = if mode == FILE_MODE_EXECUTABLE {
+                            FileKind::Executable
+                        } else {
+                            FileKind::File
+                        };
+
+                        RepoFile {
+                            size: Some(size),
+                            ..RepoFile::new(name, kind)
+                        }
+                    }
+                },
+                _ => return None,
+            };
+
+            Some(Ok(RepoFile {
+                last_commit: last_commits.get(&file.name).cloned(),
+                ..file
+            }))
+        })
+        .collect()
+}
+
+/// Load the submodule definitions from the `.gitmodules` file at the root of the given tree, as
+/// mapping from submodule path to its URL.
+fn load_gitmodules(repo: &Repository, root: &Tree<'_>) -> Result<HashMap<String, String>> {
+    let Some(entry) = root.get_name(".gitmodules") else {
+        return Ok(HashMap::new());
+    };
+    let Ok(blob) = entry.to_object(repo)?.into_blob() else {
+        return Ok(HashMap::new());
+    };
+
+    Ok(parse_gitmodules(&String::from_utf8_lossy(blob.content())))
+}
+
+/// Parse the content of a `.gitmodules` file, which uses the same format as git's config files.
+/// Only the `path` and `url` keys of `submodule` sections are considered.
+fn parse_gitmodules(content: &str) -> HashMap<String, String> {
+    let mut modules = HashMap::new();
+    let mut in_submodule = false;
+    let mut path = None;
+    let mut url = None;
+
+    let mut flush = |path: &mut Option<String>, url: &mut Option<String>| {
+        if let (Some(path), Some(url)) = (path.take(), url.take()) {
+            modules.insert(path, url);
+        }
+    };
+
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+
+        if let Some(section) = line.strip_prefix('[') {
+            flush(&mut path, &mut url);
+            in_submodule = section.trim_start().starts_with("submodule");
+            continue;
+        }
+
+        if !in_submodule {
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once('=') {
+            let value = value.trim().trim_matches('"').to_owned();
+
+            match key.trim() {
+                "path" => path = Some(value.trim_end_matches('/').to_owned()),
+                "url" => url = Some(value),
+                _ => {}
+            }
+        }
+    }
+
+    flush(&mut path, &mut url);
+
+    modules
+}
+
+/// Find the value of a `.gitattributes` attribute for the path, from the attribute files in the
+/// tree and the repo's `info/attributes`, in the same order of precedence that git uses.
+fn find_attribute(
+    repo: &Repository,
+    root: &Tree<'_>,
+    path: &Utf8Path,
+    attribute: &str,
+) -> Result<Option<String>> {
+    let mut found = None;
+    let mut apply = |content: &str, relative: &Utf8Path| {
+        for (pattern, value) in parse_gitattributes(content, attribute) {
+            if attr_pattern_matches(&pattern, relative) {
+                found = value;
+            }
+        }
+    };
+
+    let mut dir = Utf8PathBuf::new();
+    let mut tree = Some(root.clone());
+    let parents = path.parent().into_iter().flat_map(Utf8Path::components);
+
+    for component in std::iter::once(None).chain(parents.map(Some)) {
+        if let Some(component) = component {
+            dir.push(component);
+            tree = subtree_at(repo, root, Some(&dir))?;
+        }
+        let Some(tree) = &tree else {
+            break;
+        };
+
+        if let Some(entry) = tree.get_name(".gitattributes")
+            && let Ok(blob) = entry.to_object(repo)?.into_blob()
+        {
+            let relative = path.strip_prefix(&dir).unwrap_or(path);
+            apply(&String::from_utf8_lossy(blob.content()), relative);
+        }
+    }
+
+    if let Ok(content) = std::fs::read_to_string(repo.path().join("info").join("attributes")) {
+        apply(&content, path);
+    }
+
+    Ok(found)
+}
+
+/// Parse the content of a `.gitattributes` file into its patterns and the value they set for the
+/// attribute. A value of `None` means the attribute is unset again. Lines that don't touch the
+/// attribute, as well as macro definitions, are skipped.
+fn parse_gitattributes(content: &str, attribute: &str) -> Vec<(String, Option<String>)> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with("[attr]"))
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let pattern = parts.next()?;
+            let value = parts.rev().find_map(|attr| {
+                if let Some(name) = attr.strip_prefix(['-', '!']) {
+                    return (name == attribute).then_some(None);
+                }
+
+                let (name, value) = attr.split_once('=')?;
+                (name == attribute).then(|| Some(value.to_owned()))
+            })?;
+
+            Some((pattern.to_owned(), value))
+        })
+        .collect()
+}
+
+/// Check whether a `.gitattributes` pattern matches the path, given relative to the directory of
+/// the attributes file. Patterns without a slash only match the file name, others are anchored to
+/// the directory.
+fn attr_pattern_matches(pattern: &str, path: &Utf8Path) -> bool {
+    // Patterns for directories never match files.
+    if pattern.ends_with('/') {
+        return false;
+    }
+
+    let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+    let subject = if pattern.contains('/') {
+        path.as_str()
+    } else {
+        path.file_name().unwrap_or_default()
+    };
+
+    glob_match(
+        &pattern.chars().collect::<Vec<_>>(),
+        &subject.chars().collect::<Vec<_>>(),
+    )
+}
+
+/// Match a text against a glob pattern with the wildcards of git: `*` and `?` don't match slashes,
+/// `**` matches across directories and `[...]` matches a class of characters.
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    glob_match_from(pattern, text, &mut HashSet::new())
+}
+
+/// Match the remaining text against the remaining pattern. Both are always suffixes of the
+/// original ones, so their lengths identify the combinations that already failed to match, which
+/// keeps consecutive wildcards from backtracking exponentially.
+fn glob_match_from(pattern: &[char], text: &[char], failed: &mut HashSet<(usize, usize)>) -> bool {
+    let key = (pattern.len(), text.len());
+    if failed.contains(&key) {
+        return false;
+    }
+
+    let matched = match pattern {
+        [] => text.is_empty(),
+        ['*', '*', rest @ ..] => {
+            // `**/` also matches no directory at all.
+            if let ['/', after @ ..] = rest
+                && glob_match_from(after, text, failed)
+            {
+                return true;
+            }
+
+            (0..=text.len()).any(|i| glob_match_from(rest, &text[i..], failed))
+        }
+        ['*', rest @ ..] => {
+            let end = text.iter().position(|&c| c == '/').unwrap_or(text.len());
+            (0..=end).any(|i| glob_match_from(rest, &text[i..], failed))
+        }
+        ['?', rest @ ..] => {
+            matches!(text, [c, ..] if *c != '/') && glob_match_from(rest, &text[1..], failed)
+        }
+        ['[', class @ ..] => match class_match(class, text.first().copied()) {
+            Some((matched, rest)) => matched && glob_match_from(rest, &text[1..], failed),
+            // Without a closing bracket, the bracket is taken literally.
+            None => text.first() == Some(&'[') && glob_match_from(class, &text[1..], failed),
+        },
+        ['\\', c, rest @ ..] | [c, rest @ ..] => {
+            text.first() == Some(c) && glob_match_from(rest, &text[1..], failed)
+        }
+    };
+
+    if !matched {
+        failed.insert(key);
+    }
+
+    matched
+}
+
+/// Match a character against the class at the start of the pattern (after the opening bracket).
+/// Returns whether it matched and the remaining pattern after the class, or `None` if the class
+/// isn't closed.
+fn class_match(class: &[char], c: Option<char>) -> Option<(bool, &[char])> {
+    let (negated, mut class) = match class {
+        ['!' | '^', rest @ ..] => (true, rest),
+        _ => (false, class),
+    };
+    let mut matched = false;
+    let mut first = true;
+
+    loop {
+        match class {
+            [']', rest @ ..] if !first => {
+                let matched = c.is_some_and(|c| c != '/') && matched != negated;
+                return Some((matched, rest));
+            }
+            [start, '-', end, rest @ ..] if *end != ']' => {
+                matched |= c.is_some_and(|c| (*start..=*end).contains(&c));
+                class = rest;
+            }
+            [single, rest @ ..] => {
+                matched |= c == Some(*single);
+                class = rest;
+            }
+            [] => return None,
+        }
+        first = false;
+    }
+}
+
+fn repo_tree_from_tree(
+    repo: &Repository,
+    commit: &Commit<'_>,
+    path: &Utf8Path,
+    name: &str,
+    tree: &Tree<'_>,
+) -> Result<RepoTree> {
+    Ok(RepoTree {
+        name: name.to_owned(),
+        kind: TreeKind::Directory {
+            files: repo_files_from_tree(repo, commit, Some(path), tree)?,
+            readme: find_readme(repo, tree)?,
+        },
+    })
+}
+
+/// Find the README file directly inside the given tree and load its content, if it's valid UTF-8.
+/// If there are several README files, the first one in order of [`ReadmeFormat::FILE_NAMES`] is
+/// picked.
+fn find_readme(repo: &Repository, tree: &Tree<'_>) -> Result<Option<Readme>> {
+    let entry = ReadmeFormat::FILE_NAMES
+        .iter()
+        .find_map(|(file_name, format)| {
+            tree.iter()
+                .find(|entry| {
+                    entry
+                        .name()
+                        .is_some_and(|name| name.eq_ignore_ascii_case(file_name))
+                        && entry.kind().is_some_and(|kind| kind == ObjectType::Blob)
+                })
+                .map(|entry| (entry, *format))
+        });
+
+    let readme = match entry {
+        Some((entry, format)) => {
+            let blob = entry
+                .to_object(repo)
+                .context("failed converting entry to object")?
+                .into_blob()
+                .unwrap();
+            str::from_utf8(blob.content()).ok().map(|content| Readme {
+                format,
+                content: content.to_owned(),
+            })
+        }
+        None => None,
+    };
+
+    Ok(readme)
+}
+
+/// Maximum time that a code search may take, after which it stops and returns the results found
+/// so far.
+const SEARCH_TIME_LIMIT: Duration = Duration::from_secs(5);
+
+/// Maximum amount of matching lines that a code search returns.
+const SEARCH_RESULT_LIMIT: usize = 200;
+
+/// Maximum size of blobs that are searched. Anything larger is most likely generated or data
+/// that isn't worth searching.
+const SEARCH_BLOB_LIMIT: usize = 1024 * 1024;
+
+/// Amount of lines shown before and after each match of a code search.
+const SEARCH_CONTEXT: usize = 2;
+
+/// Find the lines of the content that contain the already lowercased query, together with
+/// [`SEARCH_CONTEXT`] lines around them. Overlapping or adjacent context is merged into a single
+/// hunk. At most `limit` matching lines are returned.
+fn search_content(content: &str, query: &str, limit: usize) -> Vec<Vec<SearchLine>> {
+    let lines = content.lines().collect::<Vec<_>>();
+    let matches = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.to_ascii_lowercase().contains(query))
+        .map(|(i, _)| i)
+        .take(limit)
+        .collect::<Vec<_>>();
+
+    let mut hunks: Vec<Vec<SearchLine>> = Vec::new();
+    let mut next = 0;
+
+    for &index in &matches {
+        let start = index.saturating_sub(SEARCH_CONTEXT).max(next);
+        let end = (index + SEARCH_CONTEXT + 1).min(lines.len());
+
+        // Continue the previous hunk if it's directly followed by this one.
+        if start > next || hunks.is_empty() {
+            hunks.push(Vec::new());
+        }
+        let hunk = hunks.last_mut().unwrap();
+
+        for (i, line) in lines.iter().enumerate().take(end).skip(start) {
+            hunk.push(SearchLine {
+                number: i + 1,
+                content: (*line).to_owned(),
+                matched: matches.binary_search(&i).is_ok(),
+            });
+        }
+
+        next = end;
+    }
+
+    hunks
+}
+
+type LastCommitKey = (PathBuf, Option<Utf8PathBuf>, Oid);
+type LastCommitMap = Arc<HashMap<String, LastCommit>>;
+
+/// Maximum amount of directory listings to keep in the [`LAST_COMMITS`] cache. The cache is
+/// simply cleared once full, which is good enough as the entries are cheap to re-compute for any
+/// directory that is still actively browsed.
+const LAST_COMMITS_CAPACITY: usize = 512;
+
+/// Maximum amount of commits to inspect when searching for the last commit of directory entries.
+/// Entries that weren't touched within that range are shown without commit information.
+const LAST_COMMITS_WALK_LIMIT: usize = 10_000;
+
+/// Cache of the last commits per directory entry. A commit ID identifies its whole history, so the
+/// result for the same commit and location of a repo never changes. The tree ID alone isn't enough,
+/// as different histories, like two branches or a revert, can end in the same tree.
+static LAST_COMMITS: LazyLock<Mutex<HashMap<LastCommitKey, LastCommitMap>>> =
+    LazyLock::new(Mutex::default);
+
+/// Find the last commit that touched each entry of the given tree, located at `path` within the
+/// history of `commit`. Only the first-parent history is followed, so changes that were merged
+/// in are attributed to the merge commit.
+#[instrument(skip_all)]
+fn find_last_commits(
+    repo: &Repository,
+    commit: &Commit<'_>,
+    path: Option<&Utf8Path>,
+    tree: &Tree<'_>,
+) -> Result<LastCommitMap> {
+    let key = (
+        repo.path().to_owned(),
+        path.map(ToOwned::to_owned),
+        commit.id(),
+    );
+
+    if let Some(cached) = LAST_COMMITS.lock().unwrap().get(&key) {
+        return Ok(Arc::clone(cached));
+    }
+
+    let mut pending = tree
+        .iter()
+        .filter_map(|entry| Some((entry.name()?.to_owned(), entry.id())))
+        .collect::<HashMap<_, _>>();
+    let mut found = HashMap::with_capacity(pending.len());
+
+    let mut walk = repo.revwalk().context("failed creating revwalk")?;
+    walk.push(commit.id())?;
+    walk.simplify_first_parent()?;
+
+    for oid in walk.take(LAST_COMMITS_WALK_LIMIT) {
+        if pending.is_empty() {
+            break;
+        }
+
+        let current = repo.find_commit(oid?)?;
+        let parent_tree = match current.parent(0) {
+            Ok(parent) => subtree_at(repo, &parent.tree()?, path)?,
+            Err(e) if e.code() == ErrorCode::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        // Pending entries are unchanged between the listed tree and the current commit, so the
+        // current commit touched an entry if its parent has a different version of it.
+        pending.retain(|name, id| {
+            let touched = parent_tree.as_ref().is_none_or(|parent| {
+                parent.id() != tree.id()
+                    && parent.get_name(name).is_none_or(|entry| entry.id() != *id)
+            });
+
+            if touched {
+                found.insert(name.clone(), last_commit_from_commit(&current));
+            }
+
+            !touched
+        });
+    }
+
+    let found = Arc::new(found);
+    let mut cache = LAST_COMMITS.lock().unwrap();
+
+    if cache.len() >= LAST_COMMITS_CAPACITY {
+        cache.clear();
+    }
+
+    cache.insert(key, Arc::clone(&found));
+
+    Ok(found)
+}
+
+fn subtree_at<'a>(
+    repo: &'a Repository,
+    root: &Tree<'a>,
+    path: Option<&Utf8Path>,
+) -> Result<Option<Tree<'a>>> {
+    let Some(path) = path else {
+        return Ok(Some(root.clone()));
+    };
+
+    match root.get_path(path.as_std_path()) {
+        Ok(entry) => Ok(entry.to_object(repo)?.into_tree().ok()),
+        Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn last_commit_from_commit(commit: &Commit<'_>) -> LastCommit {
+    LastCommit {
+        id: commit.id().to_string(),
+        summary: commit.summary().unwrap_or_default().to_owned(),
+        author: commit.author().name().unwrap_or_default().to_owned(),
+        time: commit.time().seconds(),
+    }
+}
+
+/// Maximum size of blobs that are loaded into memory for the tree view. Anything larger is only
+/// available through the raw endpoint.
+const BLOB_LOAD_LIMIT: usize = 16 * 1024 * 1024;
+
+/// Size of the chunks that raw blobs are streamed in.
+const RAW_BLOB_CHUNK_SIZE: usize = 64 * 1024;
+/// Number of raw blob chunks that are read ahead of the response.
+const RAW_BLOB_CHUNKS: usize = 4;
+
+/// Maximum size of text that is shown in the tree view. Longer text is cut off at this size.
+const BLOB_PREVIEW_LIMIT: usize = 1024 * 1024;
+
+fn repo_tree_from_blob(name: &str, blob: &Blob<'_>) -> RepoTree {
+    let size = blob.size() as u64;
+
+    RepoTree {
+        name: name.to_owned(),
+        kind: if blob.is_binary() {
+            TreeKind::Binary(size)
+        } else if blob.size() > BLOB_PREVIEW_LIMIT {
+            match truncate_utf8(&blob.content()[..BLOB_PREVIEW_LIMIT]) {
+                Some(preview) => TreeKind::Truncated {
+                    preview: preview.to_owned(),
+                    size,
+                },
+                None => TreeKind::Binary(size),
+            }
         } else {
             match str::from_utf8(blob.content()) {
                 Ok(content) => TreeKind::Text(content.to_owned()),
-                Err(_) => TreeKind::Binary(blob.size()),
+                Err(_) => TreeKind::Binary(size),
             }
         },
     }
 }
+
+/// Convert a prefix of UTF-8 content to a string, ending at the last full line. The cut may have
+/// split a multi-byte character, but any other invalid content is rejected.
+fn truncate_utf8(content: &[u8]) -> Option<&str> {
+    let content = match str::from_utf8(content) {
+        Ok(content) => content,
+        // An incomplete character at the very end is caused by the cut itself.
+        Err(e) if e.error_len().is_none() => {
+            // Unwrap: the content is valid up to this position.
+            str::from_utf8(&content[..e.valid_up_to()]).unwrap()
+        }
+        Err(_) => return None,
+    };
+
+    Some(content.rfind('\n').map_or(content, |pos| &content[..=pos]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utf8_truncation() {
+        assert_eq!(Some("one\n"), truncate_utf8(b"one\ntw"));
+        assert_eq!(Some("no newline"), truncate_utf8(b"no newline"));
+        assert_eq!(
+            Some("a\n"),
+            truncate_utf8("a\n\u{e9}".as_bytes()[..3].as_ref())
+        );
+        assert_eq!(
+            Some("\u{1f600}"),
+            truncate_utf8(&"\u{1f600}\u{1f600}".as_bytes()[..6])
+        );
+        assert_eq!(None, truncate_utf8(b"\xff\xfeabc"));
+    }
+
+    #[test]
+    fn path_validation() {
+        assert_eq!(
+            vec!["assets", "player.png"],
+            path_components(Utf8Path::new("assets/player.png")).unwrap()
+        );
+        assert!(path_components(Utf8Path::new("")).is_err());
+        assert!(path_components(Utf8Path::new("/etc/passwd")).is_err());
+        assert!(path_components(Utf8Path::new("assets/../../x")).is_err());
+        assert!(path_components(Utf8Path::new("a//b")).is_err());
+        assert!(path_components(Utf8Path::new(".GIT/config")).is_err());
+    }
+
+    #[test]
+    fn gitattributes_parsing() {
+        let content = "
+            # Generated code
+            *.inc linguist-language=C++ text
+            [attr]binary -diff -merge -text
+            scripts/* -text linguist-language=Shell
+            vendor/** linguist-vendored
+            *.h !linguist-language
+            *.png filter=lfs diff=lfs merge=lfs -text
+            ";
+        let attributes = parse_gitattributes(content, "linguist-language");
+
+        assert_eq!(
+            vec![
+                ("*.inc".to_owned(), Some("C++".to_owned())),
+                ("scripts/*".to_owned(), Some("Shell".to_owned())),
+                ("*.h".to_owned(), None),
+            ],
+            attributes
+        );
+        assert_eq!(
+            vec![("*.png".to_owned(), Some("lfs".to_owned()))],
+            parse_gitattributes(content, "filter")
+        );
+    }
+
+    #[test]
+    fn attr_patterns() {
+        let matches = |pattern, path| attr_pattern_matches(pattern, Utf8Path::new(path));
+
+        assert!(matches("*.inc", "src/lib/table.inc"));
+        assert!(matches("Makefile.*", "Makefile.am"));
+        assert!(!matches("*.inc", "table.incl"));
+        assert!(matches("/scripts/*", "scripts/run"));
+        assert!(!matches("scripts/*", "scripts/ci/run"));
+        assert!(!matches("scripts/*", "tools/scripts/run"));
+        assert!(matches("scripts/**", "scripts/ci/run"));
+        assert!(matches("**/ci/run", "ci/run"));
+        assert!(matches("**/ci/run", "scripts/ci/run"));
+        assert!(matches("src/**/*.rs", "src/main.rs"));
+        assert!(matches("file?.[ch]", "file1.h"));
+        assert!(!matches("file?.[!ch]", "file1.h"));
+        assert!(matches("[a-c]*", "build"));
+        assert!(!matches("docs/", "docs"));
+    }
+
+    #[test]
+    fn attr_patterns_backtracking() {
+        let name = "a".repeat(200);
+        let path = format!("{}/{name}", ["a"; 100].join("/"));
+
+        assert!(!attr_pattern_matches(
+            "*a*a*a*a*a*a*a*b",
+            Utf8Path::new(&name)
+        ));
+        assert!(!attr_pattern_matches(
+            "a/**a**a**a**a**a**a**b",
+            Utf8Path::new(&path)
+        ));
+        assert!(attr_pattern_matches(
+            "*a*a*a*a*a*a*a*a",
+            Utf8Path::new(&name)
+        ));
+    }
+
+    #[test]
+    fn content_search() {
+        let content = "a\nMatch\nb\nc\nd\ne\nf\ng\nmatch\nh\n";
+        let numbers = |hunks: &[Vec<SearchLine>]| {
+            hunks
+                .iter()
+                .map(|hunk| {
+                    hunk.iter()
+                        .map(|line| (line.number, line.matched))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            vec![
+                vec![(1, false), (2, true), (3, false), (4, false)],
+                vec![(7, false), (8, false), (9, true), (10, false)],
+            ],
+            numbers(&search_content(content, "match", 10))
+        );
+        assert_eq!(
+            vec![vec![(1, false), (2, true), (3, false), (4, false)]],
+            numbers(&search_content(content, "match", 1))
+        );
+        assert_eq!(
+            vec![vec![
+                (1, false),
+                (2, false),
+                (3, true),
+                (4, true),
+                (5, false),
+                (6, false)
+            ]],
+            numbers(&search_content("x\ny\nb\nb\nz\nw\nv\n", "b", 10))
+        );
+        assert!(search_content(content, "missing", 10).is_empty());
+    }
+
+    #[test]
+    fn gitmodules_parsing() {
+        let modules = parse_gitmodules(
+            r#"
+            # Engine and shared assets
+            [submodule "engine"]
+                path = vendor/engine
+                url = https://example.com/engine.git
+            [core]
+                url = https://example.com/ignored.git
+            [submodule "assets"]
+                url = "git@example.com:jam/assets.git"
+                path = assets/
+            [submodule "broken"]
+                path = broken
+            "#,
+        );
+
+        assert_eq!(2, modules.len());
+        assert_eq!(
+            Some("https://example.com/engine.git"),
+            modules.get("vendor/engine").map(String::as_str)
+        );
+        assert_eq!(
+            Some("git@example.com:jam/assets.git"),
+            modules.get("assets").map(String::as_str)
+        );
+    }
+
+    /// Bare repo in a temporary directory, which is removed again when dropped.
+    struct TestRepo {
+        path: PathBuf,
+        repo: Repository,
+    }
+
+    impl TestRepo {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("marmalade-test-{}-{name}", std::process::id()));
+            std::fs::remove_dir_all(&path).ok();
+            let repo = Repository::init_bare(&path).unwrap();
+
+            Self { path, repo }
+        }
+
+        /// Write a file on top of the first parent's tree, commit it and point the branch to the
+        /// new commit.
+        fn commit(&self, branch: &str, parents: &[Oid], (name, content): (&str, &str)) -> Oid {
+            let parents = parents
+                .iter()
+                .map(|id| self.repo.find_commit(*id).unwrap())
+                .collect::<Vec<_>>();
+            let base = parents.first().map(|parent| parent.tree().unwrap());
+
+            let mut builder = self.repo.treebuilder(base.as_ref()).unwrap();
+            let blob = self.repo.blob(content.as_bytes()).unwrap();
+            builder.insert(name, blob, FILE_MODE_BLOB).unwrap();
+            let tree = self.repo.find_tree(builder.write().unwrap()).unwrap();
+
+            let signature = user_signature("alice").unwrap();
+            let id = self
+                .repo
+                .commit(
+                    None,
+                    &signature,
+                    &signature,
+                    &format!("Change {name}"),
+                    &tree,
+                    &parents.iter().collect::<Vec<_>>(),
+                )
+                .unwrap();
+            self.repo
+                .reference(&format!("refs/heads/{branch}"), id, true, "test")
+                .unwrap();
+
+            id
+        }
+
+        fn branch(&self, branch: &str) -> Commit<'_> {
+            self.repo
+                .find_reference(&format!("refs/heads/{branch}"))
+                .and_then(|reference| reference.peel_to_commit())
+                .unwrap()
+        }
+    }
+
+    impl Drop for TestRepo {
+        fn drop(&mut self) {
+            std::fs::remove_dir_all(&self.path).ok();
+        }
+    }
+
+    #[test]
+    fn merge_fast_forward() {
+        let test = TestRepo::new("fast-forward");
+        let base = test.commit("main", &[], ("a.txt", "one"));
+        let head = test.commit("feature", &[base], ("b.txt", "two"));
+
+        let comparison = compare_revs(&test.repo, "main", "feature")
+            .unwrap()
+            .unwrap();
+        assert!(comparison.fast_forward);
+        assert_eq!(1, comparison.commits.len());
+        assert_eq!(1, comparison.files.len());
+        assert!(comparison.conflicts.is_empty());
+
+        let merge = |base_commit, strategy| {
+            merge_into(
+                &test.repo,
+                ("main", base_commit),
+                ("feature", head),
+                strategy,
+                "alice",
+            )
+            .unwrap()
+        };
+
+        assert_eq!(
+            MergeOutcome::BranchMoved,
+            merge(head, MergeStrategy::FastForward)
+        );
+        assert_eq!(
+            MergeOutcome::Merged,
+            merge(base, MergeStrategy::FastForward)
+        );
+        assert_eq!(head, test.branch("main").id());
+        assert_eq!(
+            MergeOutcome::UpToDate,
+            merge(head, MergeStrategy::MergeCommit)
+        );
+    }
+
+    #[test]
+    fn merge_diverged() {
+        let test = TestRepo::new("diverged");
+        let root = test.commit("main", &[], ("a.txt", "one"));
+        let head = test.commit("feature", &[root], ("b.txt", "two"));
+        let base = test.commit("main", &[root], ("c.txt", "three"));
+
+        let comparison = compare_revs(&test.repo, "main", "feature")
+            .unwrap()
+            .unwrap();
+        assert!(!comparison.fast_forward);
+        assert_eq!(1, comparison.commits.len());
+        assert!(comparison.conflicts.is_empty());
+
+        let merge = |strategy| {
+            merge_into(
+                &test.repo,
+                ("main", base),
+                ("feature", head),
+                strategy,
+                "alice",
+            )
+            .unwrap()
+        };
+
+        assert_eq!(
+            MergeOutcome::NotFastForward,
+            merge(MergeStrategy::FastForward)
+        );
+        assert_eq!(base, test.branch("main").id());
+
+        assert_eq!(MergeOutcome::Merged, merge(MergeStrategy::MergeCommit));
+        let merged = test.branch("main");
+        assert_eq!(vec![base, head], merged.parent_ids().collect::<Vec<_>>());
+        assert_eq!(Some("Merge branch 'feature' into main"), merged.message());
+        assert!(merged.tree().unwrap().get_name("b.txt").is_some());
+
+        test.repo
+            .reference("refs/heads/main", base, true, "test")
+            .unwrap();
+
+        assert_eq!(MergeOutcome::Merged, merge(MergeStrategy::Squash));
+        let squashed = test.branch("main");
+        assert_eq!(vec![base], squashed.parent_ids().collect::<Vec<_>>());
+        assert_eq!(
+            Some("Squash branch 'feature' into main\n\n* Change b.txt"),
+            squashed.message()
+        );
+        assert!(squashed.tree().unwrap().get_name("b.txt").is_some());
+    }
+
+    #[test]
+    fn merge_conflicts() {
+        let test = TestRepo::new("conflicts");
+        let root = test.commit("main", &[], ("a.txt", "one"));
+        let head = test.commit("feature", &[root], ("a.txt", "feature"));
+        let base = test.commit("main", &[root], ("a.txt", "main"));
+
+        let comparison = compare_revs(&test.repo, "main", "feature")
+            .unwrap()
+            .unwrap();
+        assert_eq!(vec!["a.txt"], comparison.conflicts);
+
+        for strategy in [MergeStrategy::MergeCommit, MergeStrategy::Squash] {
+            assert_eq!(
+                MergeOutcome::Conflicts,
+                merge_into(
+                    &test.repo,
+                    ("main", base),
+                    ("feature", head),
+                    strategy,
+                    "alice",
+                )
+                .unwrap()
+            );
+        }
+        assert_eq!(base, test.branch("main").id());
+    }
+}
@@ -48,6 +48,23 @@ impl SettingsRepository {
         STATE.read().await.tor.as_ref().map(|t| t.onion.clone())
     }
 
+    pub async fn get_default_branch(&self) -> Option<String> {
+        STATE.read().await.default_branch.clone()
+    }
+
+    pub async fn set_default_branch(&self, branch: Option<String>) -> Result<()> {
+        let mut settings = STATE.write().await;
+        let old_branch = mem::replace(&mut settings.default_branch, branch);
+
+        match save(&settings).await {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                settings.default_branch = old_branch;
+                Err(e)
+            }
+        }
+    }
+
     #[allow(clippy::option_if_let_else)]
     pub async fn set_tor_onion(&self, onion: String) -> Result<()> {
         let mut settings = STATE.write().await;
@@ -11,7 +11,10 @@ use tracing::instrument;
 use uuid::Uuid;
 
 use super::RepoRepository;
-use crate::{dirs::DIRS, models::UserAccount};
+use crate::{
+    dirs::DIRS,
+    models::{RepoSummary, Theme, UserAccount},
+};
 
 pub struct UserRepository<'a> {
     user: &'a str,
@@ -52,6 +55,8 @@ impl<'a> UserRepository<'a> {
             description: info.description.unwrap_or_default().to_owned(),
             private: info.private,
             admin: info.admin,
+            theme: Theme::default(),
+            default_branch: None,
         })?;
 
         fs::create_dir_all(DIRS.user_dir(self.user)).await?;
@@ -116,8 +121,19 @@ impl<'a> UserRepository<'a> {
 
     #[instrument(skip_all)]
     pub async fn list_repo_names(&self, auth_user: &str) -> Result<Vec<(String, String)>> {
+        let repos = self.list_repos(auth_user).await?;
+
+        Ok(repos
+            .into_iter()
+            .map(|repo| (repo.name, repo.description))
+            .collect())
+    }
+
+    /// List all repos of the user that are visible to `auth_user`, together with their info.
+    #[instrument(skip_all)]
+    pub async fn list_repos(&self, auth_user: &str) -> Result<Vec<RepoSummary>> {
         let mut entries = fs::read_dir(DIRS.user_repos_dir(self.user)).await?;
-        let mut names = Vec::new();
+        let mut repos = Vec::new();
 
         while let Some(entry) = entries.next_entry().await? {
             let path = Utf8PathBuf::try_from(entry.path())?;
@@ -126,12 +142,18 @@ impl<'a> UserRepository<'a> {
             let repo_repo = self.repo(file_name);
 
             if repo_repo.exists().await && repo_repo.visible(auth_user, self.user).await? {
-                let description = repo_repo.load_info().await.unwrap().description;
-                names.push((path.file_name().unwrap().to_owned(), description));
+                let info = repo_repo.load_info().await?;
+                repos.push(RepoSummary {
+                    user: self.user.to_owned(),
+                    name: file_name.to_owned(),
+                    description: info.description,
+                    pushed: info.pushed,
+                    template: info.template,
+                });
             }
         }
 
-        Ok(names)
+        Ok(repos)
     }
 
     #[instrument(skip_all)]
@@ -9,9 +9,15 @@ use crate::{cookies::Cookies, templates};
 
 pub struct StatusTemplate(pub StatusCode);
 
+/// Marks responses that were rendered by a [`StatusTemplate`], so the error page can be rendered
+/// again for the logged in user by the [`ErrorPageLayer`](crate::middleware::ErrorPageLayer).
+#[derive(Clone, Copy)]
+pub struct ErrorStatus(pub StatusCode);
+
 impl IntoResponse for StatusTemplate {
     fn into_response(self) -> Response {
         let mut res = templates::Error {
+            auth_user: None,
             code: self.0,
             message: None,
         }
@@ -19,6 +25,7 @@ impl IntoResponse for StatusTemplate {
 
         if res.status() != StatusCode::INTERNAL_SERVER_ERROR {
             *res.status_mut() = self.0;
+            res.extensions_mut().insert(ErrorStatus(self.0));
         }
 
         res
@@ -0,0 +1,239 @@
+//! Built-in files that new repositories can start with, as an alternative to pushing an initial
+//! commit.
+
+use camino::Utf8PathBuf;
+use serde::Deserialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum License {
+    #[serde(rename = "mit")]
+    Mit,
+    #[serde(rename = "apache-2.0")]
+    Apache2,
+    #[serde(rename = "bsd-3-clause")]
+    Bsd3Clause,
+    #[serde(rename = "gpl-3.0")]
+    Gpl3,
+    #[serde(rename = "mpl-2.0")]
+    Mpl2,
+    #[serde(rename = "unlicense")]
+    Unlicense,
+}
+
+impl License {
+    pub const ALL: &[Self] = &[
+        Self::Mit,
+        Self::Apache2,
+        Self::Bsd3Clause,
+        Self::Gpl3,
+        Self::Mpl2,
+        Self::Unlicense,
+    ];
+
+    /// Identifier as used in forms, matching the SPDX identifier in lowercase.
+    pub fn id(self) -> &'static str {
+        match self {
+            Self::Mit => "mit",
+            Self::Apache2 => "apache-2.0",
+            Self::Bsd3Clause => "bsd-3-clause",
+            Self::Gpl3 => "gpl-3.0",
+            Self::Mpl2 => "mpl-2.0",
+            Self::Unlicense => "unlicense",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Mit => "MIT License",
+            Self::Apache2 => "Apache License 2.0",
+            Self::Bsd3Clause => "BSD 3-Clause License",
+            Self::Gpl3 => "GNU General Public License v3.0",
+            Self::Mpl2 => "Mozilla Public License 2.0",
+            Self::Unlicense => "The Unlicense",
+        }
+    }
+
+    /// Full license text, with the copyright line filled in for licenses that have one.
+    pub fn text(self, owner: &str, year: i64) -> String {
+        let text = match self {
+            Self::Mit => include_str!("../assets/scaffold/licenses/MIT"),
+            Self::Apache2 => include_str!("../assets/scaffold/licenses/Apache-2.0"),
+            Self::Bsd3Clause => include_str!("../assets/scaffold/licenses/BSD-3-Clause"),
+            Self::Gpl3 => include_str!("../assets/scaffold/licenses/GPL-3.0"),
+            Self::Mpl2 => include_str!("../assets/scaffold/licenses/MPL-2.0"),
+            Self::Unlicense => include_str!("../assets/scaffold/licenses/Unlicense"),
+        };
+
+        text.replace("{year}", &year.to_string())
+            .replace("{owner}", owner)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Gitignore {
+    Godot,
+    Unity,
+    Unreal,
+    Rust,
+    Cpp,
+    Node,
+    Python,
+}
+
+impl Gitignore {
+    pub const ALL: &[Self] = &[
+        Self::Godot,
+        Self::Unity,
+        Self::Unreal,
+        Self::Rust,
+        Self::Cpp,
+        Self::Node,
+        Self::Python,
+    ];
+
+    /// Identifier as used in forms.
+    pub fn id(self) -> &'static str {
+        match self {
+            Self::Godot => "godot",
+            Self::Unity => "unity",
+            Self::Unreal => "unreal",
+            Self::Rust => "rust",
+            Self::Cpp => "cpp",
+            Self::Node => "node",
+            Self::Python => "python",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Godot => "Godot",
+            Self::Unity => "Unity",
+            Self::Unreal => "Unreal Engine",
+            Self::Rust => "Rust",
+            Self::Cpp => "C++",
+            Self::Node => "Node",
+            Self::Python => "Python",
+        }
+    }
+
+    pub fn content(self) -> &'static str {
+        match self {
+            Self::Godot => include_str!("../assets/scaffold/gitignore/Godot.gitignore"),
+            Self::Unity => include_str!("../assets/scaffold/gitignore/Unity.gitignore"),
+            Self::Unreal => include_str!("../assets/scaffold/gitignore/Unreal.gitignore"),
+            Self::Rust => include_str!("../assets/scaffold/gitignore/Rust.gitignore"),
+            Self::Cpp => include_str!("../assets/scaffold/gitignore/C++.gitignore"),
+            Self::Node => include_str!("../assets/scaffold/gitignore/Node.gitignore"),
+            Self::Python => include_str!("../assets/scaffold/gitignore/Python.gitignore"),
+        }
+    }
+}
+
+/// Files to add in the initial commit of a new repository.
+pub struct Scaffold {
+    pub readme: bool,
+    pub license: Option<License>,
+    pub gitignore: Option<Gitignore>,
+}
+
+impl Scaffold {
+    pub fn is_empty(&self) -> bool {
+        !self.readme && self.license.is_none() && self.gitignore.is_none()
+    }
+
+    /// Generate the content of all selected files. The README only consists of a title and the
+    /// description of the repo, and licenses are attributed to the owner in the current year.
+    pub fn files(&self, repo: &str, description: &str, owner: &str) -> Vec<(Utf8PathBuf, Vec<u8>)> {
+        let mut files = Vec::new();
+
+        if self.readme {
+            let description = description.trim();
+            let readme = if description.is_empty() {
+                format!("# {repo}\n")
+            } else {
+                format!("# {repo}\n\n{description}\n")
+            };
+            files.push(("README.md".into(), readme.into_bytes()));
+        }
+
+        if let Some(license) = self.license {
+            let year = current_year();
+            files.push(("LICENSE".into(), license.text(owner, year).into_bytes()));
+        }
+
+        if let Some(gitignore) = self.gitignore {
+            files.push((".gitignore".into(), gitignore.content().as_bytes().to_vec()));
+        }
+
+        files
+    }
+}
+
+fn current_year() -> i64 {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| i64::try_from(d.as_secs()).unwrap_or(i64::MAX));
+
+    year_from_timestamp(now)
+}
+
+/// Calculate the year of the Gregorian calendar that a Unix timestamp falls into, using the
+/// algorithm from <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn year_from_timestamp(secs: i64) -> i64 {
+    let days = secs.div_euclid(24 * 60 * 60) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+
+    // The algorithm counts years from March, so January and February belong to the next year.
+    year_of_era + era * 400 + i64::from(month_index >= 10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn years() {
+        assert_eq!(1970, year_from_timestamp(0));
+        assert_eq!(1969, year_from_timestamp(-1));
+        assert_eq!(1999, year_from_timestamp(946_684_799));
+        assert_eq!(2000, year_from_timestamp(946_684_800));
+        assert_eq!(2024, year_from_timestamp(1_709_251_199));
+        assert_eq!(2026, year_from_timestamp(1_798_761_599));
+        assert_eq!(2027, year_from_timestamp(1_798_761_600));
+    }
+
+    #[test]
+    fn scaffold_files() {
+        let scaffold = Scaffold {
+            readme: true,
+            license: Some(License::Mit),
+            gitignore: Some(Gitignore::Godot),
+        };
+        let files = scaffold.files("game", " A jam game\n", "alice");
+        let names = files
+            .iter()
+            .map(|(path, _)| path.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(vec!["README.md", "LICENSE", ".gitignore"], names);
+        assert_eq!(b"# game\n\nA jam game\n", files[0].1.as_slice());
+
+        let license = String::from_utf8_lossy(&files[1].1);
+        assert!(license.contains(&format!("Copyright (c) {} alice", current_year())));
+        assert!(!license.contains('{'));
+
+        let empty = Scaffold {
+            readme: false,
+            license: None,
+            gitignore: None,
+        };
+        assert!(empty.is_empty());
+        assert!(empty.files("game", "", "alice").is_empty());
+    }
+}
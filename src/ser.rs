@@ -57,7 +57,7 @@ pub mod hex {
 
         #[test]
         fn valid() {
-            const HEX:&str = "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff";
+            const HEX: &str = "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff";
             let test = Test { key: [255; 64] };
 
             assert_tokens(
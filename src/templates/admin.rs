@@ -3,8 +3,9 @@ use std::str::FromStr;
 use anyhow::bail;
 use askama::Template;
 use askama_web::WebTemplate;
+use camino::Utf8PathBuf;
 
-use crate::models::UserAccount;
+use crate::models::{SyntaxInfo, UserAccount};
 
 #[derive(Template, WebTemplate)]
 #[template(path = "admin/settings.html")]
@@ -12,12 +13,16 @@ pub struct Settings {
     pub message: Option<ServerSettingsMessage>,
     pub auth_user: Option<UserAccount>,
     pub onion: String,
+    pub default_branch: String,
+    pub syntaxes: Vec<SyntaxInfo>,
+    pub syntaxes_dir: Utf8PathBuf,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum ServerSettingsMessage {
     Success,
     FailedReset,
+    InvalidBranchName,
 }
 
 impl AsRef<str> for ServerSettingsMessage {
@@ -25,6 +30,7 @@ impl AsRef<str> for ServerSettingsMessage {
         match *self {
             Self::Success => "ServerSettingsMessage::Success",
             Self::FailedReset => "ServerSettingsMessage::FailedReset",
+            Self::InvalidBranchName => "ServerSettingsMessage::InvalidBranchName",
         }
     }
 }
@@ -36,6 +42,7 @@ impl FromStr for ServerSettingsMessage {
         Ok(match s {
             "ServerSettingsMessage::Success" => Self::Success,
             "ServerSettingsMessage::FailedReset" => Self::FailedReset,
+            "ServerSettingsMessage::InvalidBranchName" => Self::InvalidBranchName,
             _ => bail!("unknown variant `{s}`"),
         })
     }
@@ -1,13 +1,18 @@
 #![allow(clippy::struct_field_names)]
 
-use std::str::FromStr;
+use std::{
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use anyhow::bail;
 use askama::Template;
 use askama_web::WebTemplate;
 use axum::http::StatusCode;
+use percent_encoding::NON_ALPHANUMERIC;
+use serde::Deserialize;
 
-use crate::models::UserAccount;
+use crate::models::{RepoSummary, Theme, UserAccount};
 
 pub mod admin;
 pub mod repo;
@@ -19,9 +24,70 @@ pub struct Index {
     pub auth_user: Option<UserAccount>,
 }
 
+#[derive(Template, WebTemplate)]
+#[template(path = "explore.html")]
+pub struct Explore {
+    pub auth_user: Option<UserAccount>,
+    pub query: String,
+    pub sort: ExploreSort,
+    /// Current page, starting at 1.
+    pub page: usize,
+    pub pages: usize,
+    /// Total amount of repos that match the query, over all pages.
+    pub total: usize,
+    /// Users whose name matches the query. Only filled if there is a query.
+    pub users: Vec<String>,
+    pub repos: Vec<RepoSummary>,
+}
+
+impl Explore {
+    /// Pages to link to in the pagination, where `None` is a gap between them. The first and last
+    /// page are always included, together with the pages around the current one.
+    fn page_links(&self) -> Vec<Option<usize>> {
+        let mut links = Vec::new();
+
+        for page in 1..=self.pages {
+            if page == 1 || page == self.pages || page.abs_diff(self.page) <= 2 {
+                links.push(Some(page));
+            } else if links.last().is_some_and(Option::is_some) {
+                links.push(None);
+            }
+        }
+
+        links
+    }
+
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    fn page_url(&self, page: &usize) -> String {
+        format!(
+            "/explore?q={}&sort={}&page={page}",
+            percent_encoding::utf8_percent_encode(&self.query, NON_ALPHANUMERIC),
+            self.sort.as_ref(),
+        )
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExploreSort {
+    #[default]
+    Name,
+    Pushed,
+}
+
+impl AsRef<str> for ExploreSort {
+    fn as_ref(&self) -> &str {
+        match *self {
+            Self::Name => "name",
+            Self::Pushed => "pushed",
+        }
+    }
+}
+
 #[derive(Template, WebTemplate)]
 #[template(path = "login.html")]
 pub struct Login {
+    pub auth_user: Option<UserAccount>,
     pub error: Option<LoginError>,
 }
 
@@ -55,6 +121,7 @@ impl FromStr for LoginError {
 #[derive(Template, WebTemplate)]
 #[template(path = "register.html")]
 pub struct Register {
+    pub auth_user: Option<UserAccount>,
     pub error: Option<RegisterError>,
 }
 
@@ -91,10 +158,17 @@ impl FromStr for RegisterError {
 #[derive(Template, WebTemplate)]
 #[template(path = "error.html")]
 pub struct Error {
+    pub auth_user: Option<UserAccount>,
     pub code: StatusCode,
     pub message: Option<&'static str>,
 }
 
+/// Color theme of the logged in user. Anonymous visitors get the one preferred by their browser.
+#[allow(clippy::ref_option)]
+pub fn theme(auth_user: &Option<UserAccount>) -> Theme {
+    auth_user.as_ref().map_or(Theme::System, |user| user.theme)
+}
+
 #[allow(clippy::trivially_copy_pass_by_ref)]
 pub fn status_emoji(status: &StatusCode) -> char {
     match *status {
@@ -103,3 +177,165 @@ pub fn status_emoji(status: &StatusCode) -> char {
         _ => ' ',
     }
 }
+
+/// Format a size in bytes as human friendly value with binary units, like `1.5 KiB`.
+#[allow(clippy::cast_precision_loss, clippy::trivially_copy_pass_by_ref)]
+pub fn format_size(size: &u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB"];
+
+    if *size < 1024 {
+        return format!("{size} B");
+    }
+
+    let mut value = *size as f64 / 1024.0;
+    let mut unit = UNITS[0];
+
+    for next in &UNITS[1..] {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next;
+    }
+
+    format!("{value:.1} {unit}")
+}
+
+/// Get the URL if it can be opened in a browser, as submodule URLs are often SSH remotes.
+#[allow(clippy::ref_option)]
+pub fn web_url(url: &Option<String>) -> Option<&str> {
+    url.as_deref()
+        .filter(|url| url.starts_with("https://") || url.starts_with("http://"))
+}
+
+/// Escape a line of text for HTML and wrap all occurrences of the query in `<mark>` tags,
+/// ignoring ASCII case.
+pub fn mark_matches(line: &str, query: &str) -> String {
+    let mut html = String::with_capacity(line.len());
+    let lower = line.to_ascii_lowercase();
+    let query = query.to_ascii_lowercase();
+    let mut last = 0;
+
+    if !query.is_empty() {
+        for (start, _) in lower.match_indices(&query) {
+            let end = start + query.len();
+            pulldown_cmark_escape::escape_html(&mut html, &line[last..start]).unwrap();
+            html.push_str("<mark>");
+            pulldown_cmark_escape::escape_html(&mut html, &line[start..end]).unwrap();
+            html.push_str("</mark>");
+            last = end;
+        }
+    }
+
+    pulldown_cmark_escape::escape_html(&mut html, &line[last..]).unwrap();
+    html
+}
+
+/// Format a Unix timestamp (in seconds) as a human friendly time relative to now, like `3 days
+/// ago`.
+#[allow(clippy::trivially_copy_pass_by_ref)]
+pub fn relative_time(time: &i64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| i64::try_from(d.as_secs()).unwrap_or(i64::MAX));
+
+    format_relative(now.saturating_sub(*time))
+}
+
+fn format_relative(secs: i64) -> String {
+    const UNITS: &[(i64, &str)] = &[
+        (365 * 24 * 60 * 60, "year"),
+        (30 * 24 * 60 * 60, "month"),
+        (7 * 24 * 60 * 60, "week"),
+        (24 * 60 * 60, "day"),
+        (60 * 60, "hour"),
+        (60, "minute"),
+    ];
+
+    UNITS.iter().find(|(unit, _)| secs >= *unit).map_or_else(
+        || "just now".to_owned(),
+        |(unit, name)| {
+            let count = secs / unit;
+            format!("{count} {name}{} ago", if count == 1 { "" } else { "s" })
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert_eq!("0 B", format_size(&0));
+        assert_eq!("1023 B", format_size(&1023));
+        assert_eq!("1.0 KiB", format_size(&1024));
+        assert_eq!("1.5 MiB", format_size(&(1024 * 1024 * 3 / 2)));
+        assert_eq!("2048.0 TiB", format_size(&(1 << 51)));
+    }
+
+    #[test]
+    fn marked_matches() {
+        assert_eq!(
+            "let <mark>Foo</mark> = <mark>foo</mark>();",
+            mark_matches("let Foo = foo();", "foo")
+        );
+        assert_eq!("&lt;<mark>a&amp;b</mark>&gt;", mark_matches("<a&b>", "A&B"));
+        assert_eq!("no match", mark_matches("no match", "xyz"));
+        assert_eq!("empty", mark_matches("empty", ""));
+    }
+
+    #[test]
+    fn explore_page_links() {
+        let explore = |page, pages| Explore {
+            auth_user: None,
+            query: String::new(),
+            sort: ExploreSort::Name,
+            page,
+            pages,
+            total: 0,
+            users: Vec::new(),
+            repos: Vec::new(),
+        };
+
+        assert_eq!(vec![Some(1)], explore(1, 1).page_links());
+        assert_eq!(
+            vec![Some(1), Some(2), Some(3), None, Some(10)],
+            explore(1, 10).page_links()
+        );
+        assert_eq!(
+            vec![
+                Some(1),
+                None,
+                Some(3),
+                Some(4),
+                Some(5),
+                Some(6),
+                Some(7),
+                None,
+                Some(10)
+            ],
+            explore(5, 10).page_links()
+        );
+        assert_eq!(
+            "/explore?q=a%20b&sort=name&page=2",
+            Explore {
+                query: "a b".to_owned(),
+                ..explore(1, 2)
+            }
+            .page_url(&2)
+        );
+    }
+
+    #[test]
+    fn relative_times() {
+        assert_eq!("just now", format_relative(-5));
+        assert_eq!("just now", format_relative(59));
+        assert_eq!("1 minute ago", format_relative(60));
+        assert_eq!("2 hours ago", format_relative(2 * 60 * 60 + 59));
+        assert_eq!("1 day ago", format_relative(24 * 60 * 60));
+        assert_eq!("3 weeks ago", format_relative(21 * 24 * 60 * 60));
+        assert_eq!("10 months ago", format_relative(300 * 24 * 60 * 60));
+        assert_eq!("2 years ago", format_relative(2 * 365 * 24 * 60 * 60));
+    }
+}
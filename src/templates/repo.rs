@@ -5,7 +5,10 @@ use askama::Template;
 use askama_web::WebTemplate;
 use camino::Utf8PathBuf;
 
-use crate::models::{FileKind, RepoFile, RepoTree, TreeKind, UserAccount, UserRepo};
+use crate::models::{
+    BranchInfo, Comparison, DiffLineKind, DiffStatus, FileKind, Heading, MediaKind, Release,
+    RepoFile, RepoSummary, RepoTree, SearchResults, Symbol, TreeKind, UserAccount, UserRepo,
+};
 
 #[derive(Template, WebTemplate)]
 #[template(path = "repo/index.html")]
@@ -16,18 +19,33 @@ pub struct Index {
     pub branch: String,
     pub files: Vec<RepoFile>,
     pub readme: String,
+    pub toc: Vec<Heading>,
 }
 
 #[derive(Template, WebTemplate)]
 #[template(path = "repo/tree.html")]
 pub struct Tree {
+    pub message: Option<UploadMessage>,
     pub auth_user: Option<UserAccount>,
     pub user: String,
     pub repo: String,
     pub branch: String,
     pub branches: Vec<String>,
+    pub tags: Vec<String>,
     pub path: Utf8PathBuf,
     pub tree: RepoTree,
+    /// Rendered README of the current directory.
+    pub readme: Option<String>,
+    /// Lines of the current text file, as HTML.
+    pub lines: Vec<String>,
+    /// Definitions in the current text file, if it's source code.
+    pub outline: Vec<Symbol>,
+    /// Whether the source of a rendered file is shown instead.
+    pub source: bool,
+    /// Range of lines that are highlighted.
+    pub selected: Option<(usize, usize)>,
+    /// Full ID of the commit that the current revspec points to.
+    pub commit: String,
 }
 
 impl Tree {
@@ -43,6 +61,20 @@ impl Tree {
         paths
     }
 
+    /// Whether the current file can be edited, which is only possible on branches.
+    fn can_edit(&self) -> bool {
+        self.auth_user
+            .as_ref()
+            .is_some_and(|u| u.username == self.user)
+            && self.branches.contains(&self.branch)
+    }
+
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    fn is_selected(&self, line: &usize) -> bool {
+        self.selected
+            .is_some_and(|(start, end)| (start..=end).contains(line))
+    }
+
     fn path_of(&self, file: &str) -> String {
         let mut base = format!("/{}/{}/tree", self.user, self.repo);
         if !self.path.as_str().is_empty() {
@@ -57,16 +89,258 @@ impl Tree {
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum UploadMessage {
+    Success,
+    Unchanged,
+    InvalidName,
+    InvalidPath,
+    TooLarge,
+    LfsTracked,
+    BranchMoved,
+}
+
+impl AsRef<str> for UploadMessage {
+    fn as_ref(&self) -> &str {
+        match *self {
+            Self::Success => "UploadMessage::Success",
+            Self::Unchanged => "UploadMessage::Unchanged",
+            Self::InvalidName => "UploadMessage::InvalidName",
+            Self::InvalidPath => "UploadMessage::InvalidPath",
+            Self::TooLarge => "UploadMessage::TooLarge",
+            Self::LfsTracked => "UploadMessage::LfsTracked",
+            Self::BranchMoved => "UploadMessage::BranchMoved",
+        }
+    }
+}
+
+impl FromStr for UploadMessage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "UploadMessage::Success" => Self::Success,
+            "UploadMessage::Unchanged" => Self::Unchanged,
+            "UploadMessage::InvalidName" => Self::InvalidName,
+            "UploadMessage::InvalidPath" => Self::InvalidPath,
+            "UploadMessage::TooLarge" => Self::TooLarge,
+            "UploadMessage::LfsTracked" => Self::LfsTracked,
+            "UploadMessage::BranchMoved" => Self::BranchMoved,
+            _ => bail!("unknown variant `{s}`"),
+        })
+    }
+}
+
+impl PartialEq<UploadMessage> for &UploadMessage {
+    fn eq(&self, other: &UploadMessage) -> bool {
+        (*self).eq(other)
+    }
+}
+
+#[derive(Template, WebTemplate)]
+#[template(path = "repo/edit.html")]
+pub struct Edit {
+    pub auth_user: Option<UserAccount>,
+    pub user: String,
+    pub repo: String,
+    pub branch: String,
+    pub path: Utf8PathBuf,
+    /// ID of the commit that the edit is based on.
+    pub commit: String,
+    pub message: String,
+    pub content: String,
+    pub crlf: bool,
+    pub error: Option<EditError>,
+}
+
+#[derive(Clone, Copy)]
+pub enum EditError {
+    BranchMoved,
+    TooLarge,
+    LfsTracked,
+}
+
+#[derive(Template, WebTemplate)]
+#[template(path = "repo/search.html")]
+pub struct Search {
+    pub auth_user: Option<UserAccount>,
+    pub user: String,
+    pub repo: String,
+    pub branch: String,
+    pub query: String,
+    /// Results of the search, or `None` if there was no query yet.
+    pub results: Option<SearchResults>,
+}
+
+#[derive(Template, WebTemplate)]
+#[template(path = "repo/compare.html")]
+pub struct Compare {
+    pub message: Option<MergeMessage>,
+    pub auth_user: Option<UserAccount>,
+    pub user: String,
+    pub repo: String,
+    pub base: String,
+    pub head: String,
+    pub branches: Vec<String>,
+    /// Result of the comparison, or `None` if there is no head to compare yet.
+    pub comparison: Option<Comparison>,
+}
+
+impl Compare {
+    /// Whether the current user can merge the head, which is only possible into branches.
+    fn can_merge(&self) -> bool {
+        self.auth_user
+            .as_ref()
+            .is_some_and(|u| u.username == self.user)
+            && self.branches.contains(&self.base)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MergeMessage {
+    Merged,
+    UpToDate,
+    NotFastForward,
+    Conflicts,
+    BranchMoved,
+}
+
+impl AsRef<str> for MergeMessage {
+    fn as_ref(&self) -> &str {
+        match *self {
+            Self::Merged => "MergeMessage::Merged",
+            Self::UpToDate => "MergeMessage::UpToDate",
+            Self::NotFastForward => "MergeMessage::NotFastForward",
+            Self::Conflicts => "MergeMessage::Conflicts",
+            Self::BranchMoved => "MergeMessage::BranchMoved",
+        }
+    }
+}
+
+impl FromStr for MergeMessage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "MergeMessage::Merged" => Self::Merged,
+            "MergeMessage::UpToDate" => Self::UpToDate,
+            "MergeMessage::NotFastForward" => Self::NotFastForward,
+            "MergeMessage::Conflicts" => Self::Conflicts,
+            "MergeMessage::BranchMoved" => Self::BranchMoved,
+            _ => bail!("unknown variant `{s}`"),
+        })
+    }
+}
+
+impl PartialEq<MergeMessage> for &MergeMessage {
+    fn eq(&self, other: &MergeMessage) -> bool {
+        (*self).eq(other)
+    }
+}
+
+#[derive(Template, WebTemplate)]
+#[template(path = "repo/releases.html")]
+pub struct Releases {
+    pub message: Option<ReleaseMessage>,
+    pub auth_user: Option<UserAccount>,
+    pub user: String,
+    pub repo: String,
+    pub branch: String,
+    /// All releases, together with their already rendered release notes.
+    pub releases: Vec<(Release, String)>,
+}
+
+impl Releases {
+    fn auth_same_user(&self) -> bool {
+        self.auth_user
+            .as_ref()
+            .is_some_and(|u| u.username == self.user)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ReleaseMessage {
+    Success,
+    UnknownTag,
+    InvalidAssetName,
+    TagCreated,
+    TagDeleted,
+    InvalidTagName,
+    TagExists,
+    UnknownRev,
+}
+
+impl ReleaseMessage {
+    fn is_error(self) -> bool {
+        !matches!(self, Self::Success | Self::TagCreated | Self::TagDeleted)
+    }
+}
+
+impl AsRef<str> for ReleaseMessage {
+    fn as_ref(&self) -> &str {
+        match *self {
+            Self::Success => "ReleaseMessage::Success",
+            Self::UnknownTag => "ReleaseMessage::UnknownTag",
+            Self::InvalidAssetName => "ReleaseMessage::InvalidAssetName",
+            Self::TagCreated => "ReleaseMessage::TagCreated",
+            Self::TagDeleted => "ReleaseMessage::TagDeleted",
+            Self::InvalidTagName => "ReleaseMessage::InvalidTagName",
+            Self::TagExists => "ReleaseMessage::TagExists",
+            Self::UnknownRev => "ReleaseMessage::UnknownRev",
+        }
+    }
+}
+
+impl FromStr for ReleaseMessage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "ReleaseMessage::Success" => Self::Success,
+            "ReleaseMessage::UnknownTag" => Self::UnknownTag,
+            "ReleaseMessage::InvalidAssetName" => Self::InvalidAssetName,
+            "ReleaseMessage::TagCreated" => Self::TagCreated,
+            "ReleaseMessage::TagDeleted" => Self::TagDeleted,
+            "ReleaseMessage::InvalidTagName" => Self::InvalidTagName,
+            "ReleaseMessage::TagExists" => Self::TagExists,
+            "ReleaseMessage::UnknownRev" => Self::UnknownRev,
+            _ => bail!("unknown variant `{s}`"),
+        })
+    }
+}
+
+impl PartialEq<ReleaseMessage> for &ReleaseMessage {
+    fn eq(&self, other: &ReleaseMessage) -> bool {
+        (*self).eq(other)
+    }
+}
+
+#[derive(Template, WebTemplate)]
+#[template(path = "repo/tag_delete.html")]
+pub struct TagDelete {
+    pub auth_user: Option<UserAccount>,
+    pub user: String,
+    pub repo: String,
+    pub release: Release,
+}
+
 #[derive(Template, WebTemplate)]
 #[template(path = "repo/create.html")]
 pub struct Create {
+    pub auth_user: Option<UserAccount>,
     pub error: Option<RepoCreateError>,
+    /// Initial branch name that is used if none is entered, or Git's default if `None`.
+    pub default_branch: Option<String>,
+    /// Template repos that the new repo can be copied from.
+    pub templates: Vec<RepoSummary>,
 }
 
 #[derive(Clone, Copy)]
 pub enum RepoCreateError {
     InvalidName,
     AlreadyExists,
+    UnknownTemplate,
+    InvalidBranchName,
 }
 
 impl AsRef<str> for RepoCreateError {
@@ -74,6 +348,8 @@ impl AsRef<str> for RepoCreateError {
         match *self {
             Self::InvalidName => "RepoCreateError::InvalidName",
             Self::AlreadyExists => "RepoCreateError::AlreadyExists",
+            Self::UnknownTemplate => "RepoCreateError::UnknownTemplate",
+            Self::InvalidBranchName => "RepoCreateError::InvalidBranchName",
         }
     }
 }
@@ -85,6 +361,8 @@ impl FromStr for RepoCreateError {
         Ok(match s {
             "RepoCreateError::InvalidName" => Self::InvalidName,
             "RepoCreateError::AlreadyExists" => Self::AlreadyExists,
+            "RepoCreateError::UnknownTemplate" => Self::UnknownTemplate,
+            "RepoCreateError::InvalidBranchName" => Self::InvalidBranchName,
             _ => bail!("unknown variant `{s}`"),
         })
     }
@@ -93,9 +371,20 @@ impl FromStr for RepoCreateError {
 #[derive(Template, WebTemplate)]
 #[template(path = "repo/delete.html")]
 pub struct Delete {
+    pub auth_user: Option<UserAccount>,
     pub repo: String,
 }
 
+#[derive(Template, WebTemplate)]
+#[template(path = "repo/branch_delete.html")]
+pub struct BranchDelete {
+    pub auth_user: Option<UserAccount>,
+    pub user: String,
+    pub repo: String,
+    pub default_branch: String,
+    pub branch: BranchInfo,
+}
+
 #[derive(Template, WebTemplate)]
 #[template(path = "repo/settings.html")]
 pub struct Settings {
@@ -104,19 +393,44 @@ pub struct Settings {
     pub user: String,
     pub repo: String,
     pub branch: String,
-    pub branches: Vec<String>,
+    pub branches: Vec<BranchInfo>,
     pub settings: UserRepo,
 }
 
 #[derive(Clone, Copy)]
 pub enum RepoSettingsMessage {
     Success,
+    BranchCreated,
+    BranchRenamed,
+    BranchDeleted,
+    InvalidBranchName,
+    BranchExists,
+    UnknownBranch,
+    UnknownRev,
+    DefaultBranch,
+}
+
+impl RepoSettingsMessage {
+    fn is_error(self) -> bool {
+        !matches!(
+            self,
+            Self::Success | Self::BranchCreated | Self::BranchRenamed | Self::BranchDeleted
+        )
+    }
 }
 
 impl AsRef<str> for RepoSettingsMessage {
     fn as_ref(&self) -> &str {
         match *self {
             Self::Success => "RepoSettingsMessage::Success",
+            Self::BranchCreated => "RepoSettingsMessage::BranchCreated",
+            Self::BranchRenamed => "RepoSettingsMessage::BranchRenamed",
+            Self::BranchDeleted => "RepoSettingsMessage::BranchDeleted",
+            Self::InvalidBranchName => "RepoSettingsMessage::InvalidBranchName",
+            Self::BranchExists => "RepoSettingsMessage::BranchExists",
+            Self::UnknownBranch => "RepoSettingsMessage::UnknownBranch",
+            Self::UnknownRev => "RepoSettingsMessage::UnknownRev",
+            Self::DefaultBranch => "RepoSettingsMessage::DefaultBranch",
         }
     }
 }
@@ -127,6 +441,14 @@ impl FromStr for RepoSettingsMessage {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         Ok(match s {
             "RepoSettingsMessage::Success" => Self::Success,
+            "RepoSettingsMessage::BranchCreated" => Self::BranchCreated,
+            "RepoSettingsMessage::BranchRenamed" => Self::BranchRenamed,
+            "RepoSettingsMessage::BranchDeleted" => Self::BranchDeleted,
+            "RepoSettingsMessage::InvalidBranchName" => Self::InvalidBranchName,
+            "RepoSettingsMessage::BranchExists" => Self::BranchExists,
+            "RepoSettingsMessage::UnknownBranch" => Self::UnknownBranch,
+            "RepoSettingsMessage::UnknownRev" => Self::UnknownRev,
+            "RepoSettingsMessage::DefaultBranch" => Self::DefaultBranch,
             _ => bail!("unknown variant `{s}`"),
         })
     }
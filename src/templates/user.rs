@@ -36,12 +36,15 @@ pub struct Settings {
     pub auth_user: Option<UserAccount>,
     pub user: String,
     pub settings: UserAccount,
+    /// Server-wide default branch name, used unless the user sets their own.
+    pub server_branch: Option<String>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum UserSettingsMessage {
     Success,
     InvalidPassword,
+    InvalidBranchName,
 }
 
 impl AsRef<str> for UserSettingsMessage {
@@ -49,6 +52,7 @@ impl AsRef<str> for UserSettingsMessage {
         match *self {
             Self::Success => "UserSettingsMessage::Success",
             Self::InvalidPassword => "UserSettingsMessage::InvalidPassword",
+            Self::InvalidBranchName => "UserSettingsMessage::InvalidBranchName",
         }
     }
 }
@@ -60,6 +64,7 @@ impl FromStr for UserSettingsMessage {
         Ok(match s {
             "UserSettingsMessage::Success" => Self::Success,
             "UserSettingsMessage::InvalidPassword" => Self::InvalidPassword,
+            "UserSettingsMessage::InvalidBranchName" => Self::InvalidBranchName,
             _ => bail!("unknown variant `{s}`"),
         })
     }
@@ -17,3 +17,38 @@ pub fn repository(value: &str) -> bool {
             .chars()
             .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
 }
+
+/// File names for uploads into a repo. These are more relaxed than asset names, but must not
+/// contain path separators or refer to special directories.
+pub fn file_name(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 255
+        && !matches!(value, "." | "..")
+        && !value.eq_ignore_ascii_case(".git")
+        && !value.contains(['/', '\\'])
+        && !value.chars().any(char::is_control)
+}
+
+/// Names of release assets. Spaces are allowed in between, as uploads like `My Game.zip` are
+/// common, but the name must still be safe to use in a quoted `Content-Disposition` header.
+pub fn asset_name(value: &str) -> bool {
+    value.len() <= 255
+        && value.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && !value.ends_with(' ')
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ' '))
+}
+
+pub fn branch_name(value: &str) -> bool {
+    git2::Branch::name_is_valid(value).unwrap_or(false)
+}
+
+pub fn tag_name(value: &str) -> bool {
+    git2::Tag::is_valid_name(value)
+}
+
+/// Full hexadecimal commit IDs, as sent back by forms that are based on a specific commit.
+pub fn commit_id(value: &str) -> bool {
+    value.len() == 40 && value.chars().all(|c| c.is_ascii_hexdigit())
+}